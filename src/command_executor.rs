@@ -24,6 +24,17 @@ pub trait CommandExecutor {
     ) -> zed::Result<zed::process::Output>;
 }
 
+impl<E: CommandExecutor + ?Sized> CommandExecutor for Box<E> {
+    fn execute(
+        &self,
+        cmd: &str,
+        args: &[&str],
+        envs: &[(&str, &str)],
+    ) -> zed::Result<zed::process::Output> {
+        (**self).execute(cmd, args, envs)
+    }
+}
+
 /// An implementation of `CommandExecutor` that executes commands
 /// using the `zed_extension_api::Command`.
 #[cfg(feature = "command_api")]
@@ -44,3 +55,101 @@ impl CommandExecutor for RealCommandExecutor {
             .output()
     }
 }
+
+/// A `CommandExecutor` that replays a fixed script of commands in order.
+///
+/// Every call must match the next expected command and arguments, otherwise
+/// the test panics. Clones share the same script, so a single executor can be
+/// handed out to several `Bundler` and `Gemset` instances.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct ScriptedCommandExecutor {
+    script: std::rc::Rc<std::cell::RefCell<std::collections::VecDeque<ScriptedCommand>>>,
+}
+
+#[cfg(test)]
+struct ScriptedCommand {
+    cmd: String,
+    args: Vec<String>,
+    output: zed::Result<zed::process::Output>,
+}
+
+#[cfg(test)]
+impl ScriptedCommandExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn expect(&self, cmd: &str, args: &[&str], output: zed::Result<zed::process::Output>) {
+        self.script.borrow_mut().push_back(ScriptedCommand {
+            cmd: cmd.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            output,
+        });
+    }
+
+    pub fn expect_success(&self, cmd: &str, args: &[&str], stdout: &str) {
+        self.expect(
+            cmd,
+            args,
+            Ok(zed::process::Output {
+                status: Some(0),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: Vec::new(),
+            }),
+        );
+    }
+
+    pub fn expect_failure(&self, cmd: &str, args: &[&str], status: i32, stderr: &str) {
+        self.expect(
+            cmd,
+            args,
+            Ok(zed::process::Output {
+                status: Some(status),
+                stdout: Vec::new(),
+                stderr: stderr.as_bytes().to_vec(),
+            }),
+        );
+    }
+
+    pub fn assert_finished(&self) {
+        let remaining: Vec<String> = self
+            .script
+            .borrow()
+            .iter()
+            .map(|command| format!("{} {}", command.cmd, command.args.join(" ")))
+            .collect();
+        assert!(
+            remaining.is_empty(),
+            "ScriptedCommandExecutor: expected commands were not executed: {remaining:?}"
+        );
+    }
+}
+
+#[cfg(test)]
+impl CommandExecutor for ScriptedCommandExecutor {
+    fn execute(
+        &self,
+        cmd: &str,
+        args: &[&str],
+        _envs: &[(&str, &str)],
+    ) -> zed::Result<zed::process::Output> {
+        let command = self.script.borrow_mut().pop_front().unwrap_or_else(|| {
+            panic!(
+                "ScriptedCommandExecutor: unexpected command `{cmd} {}`",
+                args.join(" ")
+            )
+        });
+
+        assert_eq!(
+            cmd, command.cmd,
+            "ScriptedCommandExecutor: command mismatch"
+        );
+        assert_eq!(
+            args, command.args,
+            "ScriptedCommandExecutor: args mismatch for `{cmd}`"
+        );
+
+        command.output
+    }
+}
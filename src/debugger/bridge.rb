@@ -0,0 +1,109 @@
+# Forwards the debug adapter connection Zed opens on 127.0.0.1 to an rdbg
+# session Zed cannot connect to itself. The extension runs it as
+#
+#   ruby -e <this script> -- <listen port> <target>
+#
+# where the target is `socket <path>` for a UNIX domain socket, `pid <pid>`
+# for the session of a process, `session` for the only rdbg session open for
+# the user, or `tcp <host> <port>` for a hostname or an IPv6 address.
+require "socket"
+require "tmpdir"
+
+OPEN_HINT = "Start the program with RUBY_DEBUG_OPEN=true or `rdbg --open`, " \
+            "or call `binding.break` in it, to open a session."
+
+# The directories the debug gem opens its sockets in, in its own order.
+def socket_dirs
+  [
+    ENV["RUBY_DEBUG_SOCK_DIR"],
+    ENV["XDG_RUNTIME_DIR"],
+    File.join(Dir.tmpdir, "rdbg-#{Process.uid}"),
+    File.join(Dir.tmpdir, "ruby-debug-sock-#{Process.uid}"),
+    ENV["HOME"] && File.join(ENV["HOME"], ".rdbg-sock"),
+  ].compact.reject(&:empty?).uniq
+end
+
+# The sessions of running processes as `[path, pid, name]`, from socket names
+# such as `rdbg-1234-puma`, or `ruby-debug-alice-1234` before debug 1.5.
+def sessions
+  socket_dirs.flat_map { |dir| Dir.glob(File.join(dir, "*")) }.filter_map do |path|
+    match = /\A(?:rdbg|ruby-debug-.+?)-(\d+)(?:-(.+))?\z/.match(File.basename(path))
+    next unless match && File.socket?(path)
+
+    pid = Integer(match[1])
+    [path, pid, match[2]] if running?(pid)
+  end
+end
+
+def running?(pid)
+  Process.kill(0, pid)
+  true
+rescue Errno::EPERM
+  true
+rescue Errno::ESRCH
+  false
+end
+
+def describe(session)
+  path, pid, name = session
+  "#{name || "ruby"} (pid #{pid}) at #{path}"
+end
+
+# The socket of the session of the process `pid`.
+def find_process_session(pid)
+  session = sessions.find { |_, session_pid| session_pid == pid }
+  unless session
+    abort "Process #{pid} has no rdbg session open in #{socket_dirs.join(", ")}. #{OPEN_HINT} " \
+          "A process listening on a TCP port is attached to with `port` instead of `pid`."
+  end
+
+  warn "Attaching to #{describe(session)}"
+  session.first
+end
+
+# The socket of the only open session.
+def find_session
+  found = sessions
+  case found.size
+  when 0
+    abort "No rdbg session is open in #{socket_dirs.join(", ")}. #{OPEN_HINT}"
+  when 1
+    warn "Attaching to #{describe(found.first)}"
+    found.first.first
+  else
+    abort "Several rdbg sessions are open, set `pid` or `sock_path` to the one to attach to:\n" +
+          found.map { |session| "  #{describe(session)}\n" }.join
+  end
+end
+
+listen_port, target, *target_args = ARGV
+
+begin
+  upstream =
+    case target
+    when "socket" then UNIXSocket.new(target_args.fetch(0))
+    when "pid" then UNIXSocket.new(find_process_session(Integer(target_args.fetch(0))))
+    when "session" then UNIXSocket.new(find_session)
+    when "tcp" then TCPSocket.new(target_args.fetch(0), Integer(target_args.fetch(1)))
+    else abort "Unknown rdbg target #{target.inspect}"
+    end
+rescue SystemCallError, SocketError => e
+  abort "Unable to connect to the rdbg session: #{e.message}"
+end
+
+server = TCPServer.new("127.0.0.1", Integer(listen_port))
+client = server.accept
+server.close
+
+# Either side closing ends the session.
+done = Queue.new
+[[client, upstream], [upstream, client]].each do |from, to|
+  Thread.new do
+    IO.copy_stream(from, to)
+  rescue IOError, SystemCallError
+    nil
+  ensure
+    done << true
+  end
+end
+done.pop
@@ -0,0 +1,66 @@
+/// The script that forwards Zed's TCP connection to the session.
+const BRIDGE_SCRIPT: &str = include_str!("bridge.rb");
+
+/// An rdbg session Zed cannot connect to by itself. Zed only connects to
+/// debug adapters over TCP, to IPv4 addresses, so the extension starts `ruby`
+/// with a small script in place of rdbg. The script listens on the port Zed connects
+/// to and forwards the connection to the session.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Bridge {
+    /// The UNIX domain socket of a session, from `sock_path`.
+    Socket(String),
+    /// The session of a process, from `pid`.
+    Process(u32),
+    /// The only session open for the user, found among the sockets rdbg
+    /// creates by default.
+    Session,
+    /// A TCP port on a hostname or an IPv6 address, which the script resolves
+    /// and connects to.
+    Tcp(String, u16),
+}
+
+impl Bridge {
+    /// The `ruby` arguments that run the bridge on `listen_port`.
+    pub fn arguments(&self, listen_port: u16) -> Vec<String> {
+        let mut arguments = vec![
+            "-e".to_string(),
+            BRIDGE_SCRIPT.to_string(),
+            "--".to_string(),
+            listen_port.to_string(),
+        ];
+        match self {
+            Self::Socket(path) => arguments.extend(["socket".to_string(), path.clone()]),
+            Self::Process(pid) => arguments.extend(["pid".to_string(), pid.to_string()]),
+            Self::Session => arguments.push("session".to_string()),
+            Self::Tcp(host, port) => {
+                arguments.extend(["tcp".to_string(), host.clone(), port.to_string()])
+            }
+        }
+        arguments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridge_arguments() {
+        let target_arguments = |bridge: Bridge| {
+            let arguments = bridge.arguments(4711);
+            assert_eq!(arguments[..3], ["-e", BRIDGE_SCRIPT, "--"]);
+            arguments[3..].to_vec()
+        };
+
+        assert_eq!(
+            target_arguments(Bridge::Socket("/tmp/rdbg-1000/rdbg-42".to_string())),
+            ["4711", "socket", "/tmp/rdbg-1000/rdbg-42"]
+        );
+        assert_eq!(target_arguments(Bridge::Process(42)), ["4711", "pid", "42"]);
+        assert_eq!(target_arguments(Bridge::Session), ["4711", "session"]);
+        assert_eq!(
+            target_arguments(Bridge::Tcp("web".to_string(), 12345)),
+            ["4711", "tcp", "web", "12345"]
+        );
+    }
+}
@@ -0,0 +1,585 @@
+use super::path_map::LocalFsMap;
+use serde_yaml_ng::Value;
+
+/// The compose files `docker compose` looks for, in its order.
+pub const COMPOSE_FILES: &[&str] = &[
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+/// `docker compose` subcommands that run a service a debugger can attach to.
+const SERVICE_COMMANDS: &[&str] = &["up", "run", "start", "restart", "exec"];
+
+/// Options of `docker compose` and its subcommands that take a value.
+const VALUE_OPTIONS: &[&str] = &[
+    "-f",
+    "--file",
+    "-p",
+    "--project-name",
+    "--project-directory",
+    "--profile",
+    "--env-file",
+    "-e",
+    "--env",
+    "--name",
+    "--publish",
+    "-w",
+    "--workdir",
+    "-u",
+    "--user",
+    "-v",
+    "--volume",
+    "--entrypoint",
+    "-l",
+    "--label",
+    "--scale",
+    "-t",
+    "--timeout",
+    "--pull",
+    "--exit-code-from",
+    "--wait-timeout",
+];
+
+/// A task that runs a docker-compose service, such as `docker compose up web`.
+#[derive(Debug, PartialEq)]
+pub struct ComposeTask {
+    pub service: String,
+    /// The compose file given with `-f`.
+    pub file: Option<String>,
+}
+
+impl ComposeTask {
+    /// Reads the first service of a `docker compose` or `docker-compose`
+    /// command. `command` may hold arguments itself, as tasks often do.
+    pub fn parse(command: &str, args: &[String]) -> Option<Self> {
+        let words: Vec<&str> = command
+            .split_whitespace()
+            .chain(args.iter().map(String::as_str))
+            .collect();
+        let rest = match words.as_slice() {
+            ["docker", "compose", rest @ ..] | ["docker-compose", rest @ ..] => rest,
+            _ => return None,
+        };
+
+        let mut file = None;
+        let mut subcommand = None;
+        let mut words = rest.iter().copied();
+        while let Some(word) = words.next() {
+            if word.starts_with('-') {
+                let (option, value) = match word.split_once('=') {
+                    Some((option, value)) => (option, Some(value)),
+                    None if VALUE_OPTIONS.contains(&word) => (word, words.next()),
+                    None => (word, None),
+                };
+                if subcommand.is_none() && matches!(option, "-f" | "--file") {
+                    file = file.or(value.map(str::to_string));
+                }
+            } else if subcommand.is_none() {
+                subcommand = Some(word).filter(|word| SERVICE_COMMANDS.contains(word));
+                subcommand?;
+            } else {
+                return Some(Self {
+                    service: word.to_string(),
+                    file,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// The parts of a docker-compose service that attaching a debugger needs.
+#[derive(Debug, Default, PartialEq)]
+pub struct ComposeService {
+    pub name: String,
+    pub environment: Vec<(String, String)>,
+    pub ports: Vec<PortMapping>,
+    /// Pairs of source and target of the service's volumes.
+    pub volumes: Vec<(String, String)>,
+    pub working_dir: Option<String>,
+}
+
+/// A container port published on the host.
+#[derive(Debug, PartialEq)]
+pub struct PortMapping {
+    pub host_ip: Option<String>,
+    pub published: u16,
+    pub target: u16,
+}
+
+/// Where to attach to a service, and how its paths map to the worktree.
+#[derive(Debug, PartialEq)]
+pub struct ComposeAttach {
+    pub host: String,
+    pub port: u16,
+    pub localfs_map: LocalFsMap,
+}
+
+impl ComposeService {
+    /// Reads `name` from the `services` of a compose file, with anchors and
+    /// `<<` merge keys resolved.
+    pub fn parse(content: &str, name: &str) -> Result<Self, String> {
+        let mut root: Value = serde_yaml_ng::from_str(content).map_err(|e| e.to_string())?;
+        root.apply_merge().map_err(|e| e.to_string())?;
+        let service = root
+            .get("services")
+            .ok_or("no `services` found")?
+            .get(name)
+            .ok_or_else(|| format!("no service '{name}' found"))?;
+
+        let mut parsed = Self {
+            name: name.to_string(),
+            working_dir: service.get("working_dir").and_then(scalar),
+            ..Self::default()
+        };
+
+        match service.get("environment") {
+            Some(Value::Mapping(variables)) => {
+                for (key, value) in variables {
+                    if let Some(key) = scalar(key) {
+                        parsed
+                            .environment
+                            .push((key, scalar(value).unwrap_or_default()));
+                    }
+                }
+            }
+            Some(Value::Sequence(variables)) => {
+                for variable in variables.iter().filter_map(scalar) {
+                    if let Some((key, value)) = variable.split_once('=') {
+                        parsed
+                            .environment
+                            .push((key.to_string(), value.to_string()));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for port in items(service.get("ports")) {
+            let mapping = match port {
+                Value::Mapping(_) => (|| {
+                    Some(PortMapping {
+                        host_ip: port.get("host_ip").and_then(scalar),
+                        published: scalar(port.get("published")?)?.parse().ok()?,
+                        target: scalar(port.get("target")?)?.parse().ok()?,
+                    })
+                })(),
+                port => scalar(port).and_then(|port| parse_port(&port)),
+            };
+            parsed.ports.extend(mapping);
+        }
+
+        for volume in items(service.get("volumes")) {
+            let volume = match volume {
+                Value::Mapping(_) => volume
+                    .get("source")
+                    .and_then(scalar)
+                    .zip(volume.get("target").and_then(scalar)),
+                volume => scalar(volume).and_then(|volume| {
+                    let mut parts = volume.split(':');
+                    Some((parts.next()?.to_string(), parts.next()?.to_string()))
+                }),
+            };
+            parsed.volumes.extend(volume);
+        }
+
+        Ok(parsed)
+    }
+
+    fn env(&self, key: &str) -> Option<&str> {
+        self.environment
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Attaches through the host port that publishes the service's
+    /// `RUBY_DEBUG_PORT`, mapping its bind mounts, or else its `working_dir`,
+    /// to the worktree.
+    pub fn attach(&self, root: &str) -> Result<ComposeAttach, String> {
+        let name = &self.name;
+        let debug_port: u16 = self
+            .env("RUBY_DEBUG_PORT")
+            .ok_or_else(|| {
+                format!("Service '{name}' does not set RUBY_DEBUG_PORT in its `environment`")
+            })?
+            .parse()
+            .map_err(|_| format!("Service '{name}' sets an invalid RUBY_DEBUG_PORT"))?;
+
+        if let Some(host) = self
+            .env("RUBY_DEBUG_HOST")
+            .filter(|host| matches!(*host, "127.0.0.1" | "localhost" | "::1"))
+        {
+            return Err(format!(
+                "Service '{name}' listens on RUBY_DEBUG_HOST={host}, which cannot be \
+                 reached from outside the container, set it to 0.0.0.0"
+            ));
+        }
+
+        let mapping = self
+            .ports
+            .iter()
+            .find(|mapping| mapping.target == debug_port)
+            .ok_or_else(|| {
+                format!(
+                    "Service '{name}' does not publish RUBY_DEBUG_PORT {debug_port}, \
+                     add \"{debug_port}:{debug_port}\" to its `ports`"
+                )
+            })?;
+
+        let mut localfs_map = LocalFsMap::default();
+        for (source, target) in &self.volumes {
+            if source.starts_with(['.', '/']) && target.starts_with('/') {
+                localfs_map.insert(target, source, root)?;
+            }
+        }
+        if localfs_map.is_empty() {
+            if let Some(working_dir) = &self.working_dir {
+                localfs_map.insert(working_dir, ".", root)?;
+            }
+        }
+
+        Ok(ComposeAttach {
+            host: mapping
+                .host_ip
+                .clone()
+                .unwrap_or_else(|| "127.0.0.1".to_string()),
+            port: mapping.published,
+            localfs_map,
+        })
+    }
+}
+
+/// Parses the short port syntax, `[host_ip:]published:target[/protocol]`.
+/// Ports that are not published and port ranges are skipped.
+fn parse_port(port: &str) -> Option<PortMapping> {
+    let port = port.split('/').next()?;
+    let mut parts = port.rsplitn(3, ':');
+    let target = parts.next()?.parse().ok()?;
+    let published = parts.next()?.parse().ok()?;
+    let host_ip = parts.next().map(|host_ip| {
+        host_ip
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string()
+    });
+
+    Some(PortMapping {
+        host_ip,
+        published,
+        target,
+    })
+}
+
+/// A string, number or boolean as the string compose reads it as.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// The items of a sequence, or none for any other value.
+fn items(value: Option<&Value>) -> &[Value] {
+    match value {
+        Some(Value::Sequence(items)) => items,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSE: &str = r#"
+services:
+  db:
+    image: postgres:16
+    ports:
+      - "5432:5432"
+
+  web:
+    build: .
+    command: bin/rails server -b 0.0.0.0 # started by bin/dev locally
+    working_dir: /app
+    environment:
+      RUBY_DEBUG_OPEN: "true"
+      RUBY_DEBUG_HOST: 0.0.0.0
+      RUBY_DEBUG_PORT: 12345
+    ports:
+      - "3000:3000"
+      - 127.0.0.1:12346:12345/tcp
+    volumes:
+      - .:/app:cached
+      - bundle:/usr/local/bundle
+      - type: bind
+        source: ../engines
+        target: /engines
+
+  worker:
+    working_dir: /app
+    environment: ["RUBY_DEBUG_PORT=23456", RAILS_ENV=development]
+    ports:
+      - target: 23456
+        published: "23456"
+
+volumes:
+  bundle:
+"#;
+
+    #[test]
+    fn test_parse_compose_service() {
+        let web = ComposeService::parse(COMPOSE, "web").unwrap();
+        assert_eq!(
+            web,
+            ComposeService {
+                name: "web".to_string(),
+                environment: vec![
+                    ("RUBY_DEBUG_OPEN".to_string(), "true".to_string()),
+                    ("RUBY_DEBUG_HOST".to_string(), "0.0.0.0".to_string()),
+                    ("RUBY_DEBUG_PORT".to_string(), "12345".to_string()),
+                ],
+                ports: vec![
+                    PortMapping {
+                        host_ip: None,
+                        published: 3000,
+                        target: 3000,
+                    },
+                    PortMapping {
+                        host_ip: Some("127.0.0.1".to_string()),
+                        published: 12346,
+                        target: 12345,
+                    },
+                ],
+                volumes: vec![
+                    (".".to_string(), "/app".to_string()),
+                    ("bundle".to_string(), "/usr/local/bundle".to_string()),
+                    ("../engines".to_string(), "/engines".to_string()),
+                ],
+                working_dir: Some("/app".to_string()),
+            }
+        );
+
+        let worker = ComposeService::parse(COMPOSE, "worker").unwrap();
+        assert_eq!(
+            worker.environment,
+            vec![
+                ("RUBY_DEBUG_PORT".to_string(), "23456".to_string()),
+                ("RAILS_ENV".to_string(), "development".to_string()),
+            ]
+        );
+        assert_eq!(
+            worker.ports,
+            vec![PortMapping {
+                host_ip: None,
+                published: 23456,
+                target: 23456,
+            }]
+        );
+
+        assert_eq!(
+            ComposeService::parse(COMPOSE, "cache"),
+            Err("no service 'cache' found".to_string())
+        );
+        assert_eq!(
+            ComposeService::parse("version: '3'", "web"),
+            Err("no `services` found".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_indentless_sequences() {
+        let compose = "\
+services:
+  web:
+    environment:
+    - RUBY_DEBUG_PORT=12345
+    ports:
+    - \"12345:12345\"
+    - target: 3000
+      published: 3000
+    volumes:
+    - .:/app
+";
+        let web = ComposeService::parse(compose, "web").unwrap();
+        assert_eq!(
+            web.environment,
+            vec![("RUBY_DEBUG_PORT".to_string(), "12345".to_string())]
+        );
+        assert_eq!(
+            web.ports,
+            vec![
+                PortMapping {
+                    host_ip: None,
+                    published: 12345,
+                    target: 12345,
+                },
+                PortMapping {
+                    host_ip: None,
+                    published: 3000,
+                    target: 3000,
+                },
+            ]
+        );
+        assert_eq!(web.volumes, vec![(".".to_string(), "/app".to_string())]);
+        assert_eq!(web.attach("/root").unwrap().port, 12345);
+    }
+
+    #[test]
+    fn test_parse_anchors_and_merge_keys() {
+        let compose = "\
+x-debug: &debug
+  environment: &debug-env
+    RUBY_DEBUG_OPEN: \"true\"
+    RUBY_DEBUG_PORT: 12345
+  ports: [\"12345:12345\"]
+
+services:
+  web:
+    <<: *debug
+    working_dir: /app
+  worker:
+    environment:
+      <<: *debug-env
+      RAILS_ENV: development
+";
+        let web = ComposeService::parse(compose, "web").unwrap();
+        assert_eq!(web.working_dir.as_deref(), Some("/app"));
+        assert_eq!(web.attach("/root").unwrap().port, 12345);
+
+        let worker = ComposeService::parse(compose, "worker").unwrap();
+        assert_eq!(
+            worker.environment,
+            vec![
+                ("RAILS_ENV".to_string(), "development".to_string()),
+                ("RUBY_DEBUG_OPEN".to_string(), "true".to_string()),
+                ("RUBY_DEBUG_PORT".to_string(), "12345".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_quoted_flow_items() {
+        let compose = "\
+services:
+  web:
+    environment: [\"ALLOWED_HOSTS=a,b\", 'RUBY_DEBUG_PORT=12345']
+";
+        assert_eq!(
+            ComposeService::parse(compose, "web").unwrap().environment,
+            vec![
+                ("ALLOWED_HOSTS".to_string(), "a,b".to_string()),
+                ("RUBY_DEBUG_PORT".to_string(), "12345".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_compose_task() {
+        let task = |command: &str, args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            ComposeTask::parse(command, &args)
+        };
+
+        assert_eq!(
+            task("docker compose up web", &[]),
+            Some(ComposeTask {
+                service: "web".to_string(),
+                file: None,
+            })
+        );
+        assert_eq!(
+            task(
+                "docker",
+                &[
+                    "compose",
+                    "-f",
+                    "deploy/compose.yml",
+                    "run",
+                    "--rm",
+                    "-e",
+                    "A=1",
+                    "worker",
+                    "bin/jobs"
+                ]
+            ),
+            Some(ComposeTask {
+                service: "worker".to_string(),
+                file: Some("deploy/compose.yml".to_string()),
+            })
+        );
+        assert_eq!(
+            task("docker-compose --file=compose.dev.yml up -d", &["web"]),
+            Some(ComposeTask {
+                service: "web".to_string(),
+                file: Some("compose.dev.yml".to_string()),
+            })
+        );
+        assert_eq!(task("docker compose up", &[]), None);
+        assert_eq!(task("docker compose build web", &[]), None);
+        assert_eq!(task("bin/rails", &["server"]), None);
+    }
+
+    #[test]
+    fn test_parse_port() {
+        assert_eq!(
+            parse_port("[::1]:12346:12345"),
+            Some(PortMapping {
+                host_ip: Some("::1".to_string()),
+                published: 12346,
+                target: 12345,
+            })
+        );
+        assert_eq!(parse_port("12345"), None);
+        assert_eq!(parse_port("3000-3005:3000-3005"), None);
+    }
+
+    #[test]
+    fn test_compose_attach() {
+        let web = ComposeService::parse(COMPOSE, "web").unwrap();
+        let attach = web.attach("/home/alice/shop").unwrap();
+        assert_eq!(attach.host, "127.0.0.1");
+        assert_eq!(attach.port, 12346);
+        assert_eq!(
+            attach.localfs_map.to_string(),
+            "/engines:/home/alice/engines,/app:/home/alice/shop"
+        );
+
+        let worker = ComposeService::parse(COMPOSE, "worker").unwrap();
+        let attach = worker.attach("/home/alice/shop").unwrap();
+        assert_eq!(attach.port, 23456);
+        assert_eq!(attach.localfs_map.to_string(), "/app:/home/alice/shop");
+    }
+
+    #[test]
+    fn test_compose_attach_errors() {
+        let db = ComposeService::parse(COMPOSE, "db").unwrap();
+        assert_eq!(
+            db.attach("/root"),
+            Err("Service 'db' does not set RUBY_DEBUG_PORT in its `environment`".to_string())
+        );
+
+        let mut web = ComposeService::parse(COMPOSE, "web").unwrap();
+        web.ports.remove(1);
+        assert_eq!(
+            web.attach("/root"),
+            Err("Service 'web' does not publish RUBY_DEBUG_PORT 12345, \
+                 add \"12345:12345\" to its `ports`"
+                .to_string())
+        );
+
+        web.environment[1].1 = "localhost".to_string();
+        assert_eq!(
+            web.attach("/root"),
+            Err(
+                "Service 'web' listens on RUBY_DEBUG_HOST=localhost, which cannot be \
+                 reached from outside the container, set it to 0.0.0.0"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::RubyDebugConfig;
+
+/// A `skip_path` entry matching the files of installed gems, including git
+/// gems under `bundler/gems`. rdbg reads `/.../` entries as regular expressions.
+const BUNDLED_GEMS_PATTERN: &str = r"/\/gems\//";
+
+/// Which processes rdbg keeps debugging after a `fork`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForkMode {
+    Both,
+    Parent,
+    Child,
+}
+
+impl ForkMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Both => "both",
+            Self::Parent => "parent",
+            Self::Child => "child",
+        }
+    }
+}
+
+impl RubyDebugConfig {
+    /// The rdbg flags that open the session and decide whether the program
+    /// pauses before running: it does unless `stop_on_entry` is false or
+    /// `nonstop` is set. `debug_env` holds the variables the config sets.
+    pub fn launch_arguments(
+        &self,
+        debug_env: &HashMap<String, String>,
+    ) -> Result<Vec<String>, String> {
+        let stop_on_entry = match (self.stop_on_entry, self.nonstop) {
+            (Some(true), Some(true)) => {
+                return Err("`stop_on_entry` and `nonstop` cannot both be set".to_string())
+            }
+            (Some(stop_on_entry), _) => stop_on_entry,
+            (None, nonstop) => !nonstop.unwrap_or(false),
+        };
+
+        let mut arguments = Vec::new();
+        if !debug_env.contains_key("RUBY_DEBUG_OPEN") {
+            arguments.push("--open".to_string());
+        }
+        arguments.push(if stop_on_entry {
+            "--stop-at-load".to_string()
+        } else {
+            "--nonstop".to_string()
+        });
+        Ok(arguments)
+    }
+
+    /// The `RUBY_DEBUG_*` variables for `skip_path`, `skip_bundled` and
+    /// `fork_mode`, leaving out the ones `debug_env` sets itself.
+    pub fn launch_env(
+        &self,
+        debug_env: &HashMap<String, String>,
+    ) -> Result<Vec<(String, String)>, String> {
+        // rdbg separates `skip_path` entries with `:`.
+        if let Some(path) = self
+            .skip_path
+            .iter()
+            .find(|path| path.is_empty() || path.contains(':'))
+        {
+            return Err(format!(
+                "Invalid `skip_path` entry '{path}', entries must be non-empty and cannot contain `:`"
+            ));
+        }
+
+        let mut skip_path = self.skip_path.clone();
+        if self.skip_bundled == Some(true) {
+            skip_path.push(BUNDLED_GEMS_PATTERN.to_string());
+        }
+
+        let mut env = Vec::new();
+        if !skip_path.is_empty() {
+            env.push(("RUBY_DEBUG_SKIP_PATH".to_string(), skip_path.join(":")));
+        }
+        if let Some(fork_mode) = self.fork_mode {
+            env.push((
+                "RUBY_DEBUG_FORK_MODE".to_string(),
+                fork_mode.as_str().to_string(),
+            ));
+        }
+        env.retain(|(key, _)| !debug_env.contains_key(key));
+        Ok(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RubyDebugConfig {
+        RubyDebugConfig::default()
+    }
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_launch_arguments_stop_on_entry_by_default() {
+        assert_eq!(
+            config().launch_arguments(&env(&[])),
+            Ok(vec!["--open".to_string(), "--stop-at-load".to_string()])
+        );
+        assert_eq!(
+            config().launch_arguments(&env(&[("RUBY_DEBUG_OPEN", "true")])),
+            Ok(vec!["--stop-at-load".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_launch_arguments_without_stopping() {
+        let mut config = config();
+        config.stop_on_entry = Some(false);
+        assert_eq!(
+            config.launch_arguments(&env(&[])),
+            Ok(vec!["--open".to_string(), "--nonstop".to_string()])
+        );
+
+        config.stop_on_entry = None;
+        config.nonstop = Some(true);
+        assert_eq!(
+            config.launch_arguments(&env(&[])),
+            Ok(vec!["--open".to_string(), "--nonstop".to_string()])
+        );
+
+        config.nonstop = Some(false);
+        assert_eq!(
+            config.launch_arguments(&env(&[])),
+            Ok(vec!["--open".to_string(), "--stop-at-load".to_string()])
+        );
+
+        config.stop_on_entry = Some(true);
+        config.nonstop = Some(true);
+        assert_eq!(
+            config.launch_arguments(&env(&[])),
+            Err("`stop_on_entry` and `nonstop` cannot both be set".to_string())
+        );
+    }
+
+    #[test]
+    fn test_launch_env() {
+        assert_eq!(config().launch_env(&env(&[])), Ok(Vec::new()));
+
+        let config: RubyDebugConfig = serde_json::from_value(serde_json::json!({
+            "skip_path": ["/usr/lib/ruby", "lib/ext"],
+            "skip_bundled": true,
+            "fork_mode": "parent",
+        }))
+        .unwrap();
+        assert_eq!(
+            config.launch_env(&env(&[])),
+            Ok(vec![
+                (
+                    "RUBY_DEBUG_SKIP_PATH".to_string(),
+                    r"/usr/lib/ruby:lib/ext:/\/gems\//".to_string()
+                ),
+                ("RUBY_DEBUG_FORK_MODE".to_string(), "parent".to_string()),
+            ])
+        );
+        assert_eq!(
+            config.launch_env(&env(&[("RUBY_DEBUG_FORK_MODE", "both")])),
+            Ok(vec![(
+                "RUBY_DEBUG_SKIP_PATH".to_string(),
+                r"/usr/lib/ruby:lib/ext:/\/gems\//".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_launch_env_rejects_separators_in_skip_path() {
+        let mut config = config();
+        config.skip_path = vec!["C:/Ruby".to_string()];
+        assert_eq!(
+            config.launch_env(&env(&[])),
+            Err("Invalid `skip_path` entry 'C:/Ruby', entries must be non-empty and cannot contain `:`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fork_mode_rejects_unknown_values() {
+        let error = serde_json::from_value::<RubyDebugConfig>(serde_json::json!({
+            "fork_mode": "neither",
+        }));
+        assert!(error.is_err());
+    }
+}
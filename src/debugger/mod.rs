@@ -0,0 +1,286 @@
+mod bridge;
+mod compose;
+mod launch;
+mod path_map;
+mod plan;
+mod tcp;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+pub use bridge::Bridge;
+pub use compose::{ComposeService, ComposeTask, COMPOSE_FILES};
+pub use launch::ForkMode;
+pub use path_map::{LocalFsMap, LocalFsMapConfig};
+pub use plan::{LaunchPlan, LaunchProgram};
+pub use tcp::TcpTarget;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct RubyDebugConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script_or_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// A dotenv file, relative to the worktree root, applied before `env`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// The process to attach to, as chosen in Zed's process picker, through
+    /// the rdbg socket it opened.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// The UNIX domain socket of the rdbg session to attach to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sock_path: Option<String>,
+    /// The address rdbg listens on, or attaches to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Whether rdbg reads source files the debugger opens from the local disk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub localfs: Option<bool>,
+    /// Directories of the debuggee mapped to local ones, see [`LocalFsMap`].
+    #[serde(rename = "localfsMap", skip_serializing_if = "Option::is_none")]
+    pub localfs_map: Option<LocalFsMapConfig>,
+    /// A docker-compose service to attach to through its published
+    /// `RUBY_DEBUG_PORT`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compose_service: Option<String>,
+    /// The compose file declaring `compose_service`, relative to the worktree
+    /// root, instead of the ones `docker compose` looks for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compose_file: Option<String>,
+    /// Whether a launched program pauses before its first line, which it
+    /// does by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_on_entry: Option<bool>,
+    /// rdbg's `--nonstop`, the same as setting `stop_on_entry` to false.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonstop: Option<bool>,
+    /// Paths whose frames the debugger skips when stepping and in backtraces.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skip_path: Vec<String>,
+    /// Whether to skip the frames of installed gems.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_bundled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_mode: Option<ForkMode>,
+}
+
+impl RubyDebugConfig {
+    /// Validates `localfsMap`, resolving its local directories against `root`.
+    pub fn local_fs_map(&self, root: &str) -> Result<Option<LocalFsMap>, String> {
+        self.localfs_map
+            .as_ref()
+            .map(|map| LocalFsMap::new(map, root).map_err(|e| format!("Invalid `localfsMap`: {e}")))
+            .transpose()
+    }
+
+    /// How an attach request reaches a session Zed cannot connect to itself:
+    /// the socket in `sock_path` or `RUBY_DEBUG_SOCK_PATH`, the socket of
+    /// `pid`, a hostname or IPv6 `target`, or, without a port to connect to,
+    /// the only session open on a socket. `None` when Zed connects to
+    /// `target` directly.
+    pub fn attach_bridge(
+        &self,
+        target: &TcpTarget,
+        debug_env: &HashMap<String, String>,
+    ) -> Result<Option<Bridge>, String> {
+        if let Some(path) = self
+            .sock_path
+            .as_ref()
+            .or_else(|| debug_env.get("RUBY_DEBUG_SOCK_PATH"))
+        {
+            Ok(Some(Bridge::Socket(path.clone())))
+        } else if let Some(pid) = self.pid {
+            Ok(Some(Bridge::Process(pid)))
+        } else if let Some(host) = &target.remote {
+            let port = target
+                .port
+                .ok_or_else(|| format!("Attaching to {host} requires a `port`"))?;
+            Ok(Some(Bridge::Tcp(host.to_string(), port)))
+        } else if target.port.is_none() {
+            Ok(Some(Bridge::Session))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Fills `host`, `port` and `localfsMap` from `compose_service`, keeping
+    /// the ones the config sets. `read_file` reads a file of the worktree.
+    pub fn apply_compose_service(
+        &mut self,
+        root: &str,
+        read_file: impl Fn(&str) -> Result<String, String>,
+    ) -> Result<(), String> {
+        let Some(service) = &self.compose_service else {
+            return Ok(());
+        };
+        let (file, content) = match &self.compose_file {
+            Some(file) => (
+                file.as_str(),
+                read_file(file).map_err(|e| format!("Failed to read {file}: {e}"))?,
+            ),
+            None => COMPOSE_FILES
+                .iter()
+                .find_map(|file| read_file(file).ok().map(|content| (*file, content)))
+                .ok_or_else(|| {
+                    format!(
+                        "No compose file found for `compose_service`, add one of {} \
+                         or set `compose_file`",
+                        COMPOSE_FILES.join(", ")
+                    )
+                })?,
+        };
+
+        let attach = ComposeService::parse(&content, service)
+            .map_err(|e| format!("Invalid {file}: {e}"))?
+            .attach(root)?;
+        self.host.get_or_insert(attach.host);
+        self.port.get_or_insert(attach.port);
+        if self.localfs_map.is_none() && !attach.localfs_map.is_empty() {
+            self.localfs_map = Some(LocalFsMapConfig::Rdbg(attach.localfs_map.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RubyDebugConfig {
+        RubyDebugConfig::default()
+    }
+
+    #[test]
+    fn test_serialize_skips_unset_fields() {
+        let config = RubyDebugConfig {
+            command: Some("bin/rails".to_string()),
+            args: vec!["server".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(config).unwrap(),
+            serde_json::json!({ "command": "bin/rails", "args": ["server"] })
+        );
+    }
+
+    #[test]
+    fn test_attach_bridge() {
+        let target = |port: Option<u16>| TcpTarget {
+            port,
+            ..Default::default()
+        };
+        let no_env = HashMap::new();
+
+        let mut config = config();
+        assert_eq!(
+            config.attach_bridge(&target(None), &no_env).unwrap(),
+            Some(Bridge::Session)
+        );
+        assert_eq!(
+            config.attach_bridge(&target(Some(12345)), &no_env).unwrap(),
+            None
+        );
+
+        let env = HashMap::from([(
+            "RUBY_DEBUG_SOCK_PATH".to_string(),
+            "/tmp/rdbg-1000/rdbg-7".to_string(),
+        )]);
+        assert_eq!(
+            config.attach_bridge(&target(Some(12345)), &env).unwrap(),
+            Some(Bridge::Socket("/tmp/rdbg-1000/rdbg-7".to_string()))
+        );
+
+        config.pid = Some(42);
+        assert_eq!(
+            config.attach_bridge(&target(Some(12345)), &no_env).unwrap(),
+            Some(Bridge::Process(42))
+        );
+
+        config.sock_path = Some("/run/user/1000/rdbg-42-puma".to_string());
+        assert_eq!(
+            config.attach_bridge(&target(None), &env).unwrap(),
+            Some(Bridge::Socket("/run/user/1000/rdbg-42-puma".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_attach_bridge_to_remote_host() {
+        let resolve = |host: &str, port: Option<u16>| {
+            let config = RubyDebugConfig {
+                host: Some(host.to_string()),
+                port,
+                ..Default::default()
+            };
+            let target = TcpTarget::resolve(&config, &HashMap::new(), None, None).unwrap();
+            config.attach_bridge(&target, &HashMap::new())
+        };
+
+        assert_eq!(
+            resolve("web", Some(12345)),
+            Ok(Some(Bridge::Tcp("web".to_string(), 12345)))
+        );
+        assert_eq!(
+            resolve("[fd00::5]", Some(12345)),
+            Ok(Some(Bridge::Tcp("fd00::5".to_string(), 12345)))
+        );
+        assert_eq!(resolve("10.0.0.5", Some(12345)), Ok(None));
+        assert_eq!(
+            resolve("web", None),
+            Err("Attaching to web requires a `port`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply_compose_service() {
+        let compose = "services:\n  web:\n    environment:\n      - RUBY_DEBUG_PORT=12345\n    ports:\n      - 12345:12345\n    volumes:\n      - ./app:/rails/app\n";
+        let read_file = |file: &str| match file {
+            "docker-compose.yml" => Ok(compose.to_string()),
+            _ => Err("not found".to_string()),
+        };
+
+        let mut config = config();
+        config.compose_service = Some("web".to_string());
+        config
+            .apply_compose_service("/home/alice/shop", read_file)
+            .unwrap();
+        assert_eq!(config.host.as_deref(), Some("127.0.0.1"));
+        assert_eq!(config.port, Some(12345));
+        assert_eq!(
+            config
+                .local_fs_map("/home/alice/shop")
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            "/rails/app:/home/alice/shop/app"
+        );
+
+        let mut config = self::config();
+        config.compose_service = Some("web".to_string());
+        config.port = Some(4000);
+        config.localfs_map = Some(LocalFsMapConfig::Rdbg("/rails:.".to_string()));
+        config.apply_compose_service("/root", read_file).unwrap();
+        assert_eq!(config.port, Some(4000));
+        assert_eq!(
+            config.localfs_map,
+            Some(LocalFsMapConfig::Rdbg("/rails:.".to_string()))
+        );
+
+        config.compose_file = Some("deploy/compose.yml".to_string());
+        assert_eq!(
+            config.apply_compose_service("/root", read_file),
+            Err("Failed to read deploy/compose.yml: not found".to_string())
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+/// `localfsMap` as written in a debug config: rdbg's own
+/// `remote:local[,remote:local]` string, or an object from remote to local
+/// directories.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LocalFsMapConfig {
+    Rdbg(String),
+    Paths(BTreeMap<String, String>),
+}
+
+/// Maps directories of the debuggee, such as `/app` in a container, to the
+/// directories Zed opened, so that breakpoints bind and stack frames resolve.
+#[derive(Debug, Default, PartialEq)]
+pub struct LocalFsMap {
+    /// Pairs of remote and local directories, the longest remote one first so
+    /// that nested mounts win over the directory containing them.
+    mappings: Vec<(String, String)>,
+}
+
+impl LocalFsMap {
+    /// Validates the mappings, resolving relative local directories against
+    /// the worktree root.
+    pub fn new(config: &LocalFsMapConfig, root: &str) -> Result<Self, String> {
+        let mappings: Vec<(&str, &str)> = match config {
+            LocalFsMapConfig::Rdbg(map) => map
+                .split(',')
+                .map(str::trim)
+                .filter(|mapping| !mapping.is_empty())
+                .map(|mapping| {
+                    mapping.split_once(':').ok_or_else(|| {
+                        format!("expected `remote:local` directories, got '{mapping}'")
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            LocalFsMapConfig::Paths(map) => map
+                .iter()
+                .map(|(remote, local)| (remote.as_str(), local.as_str()))
+                .collect(),
+        };
+        if mappings.is_empty() {
+            return Err("no directories are mapped".to_string());
+        }
+
+        let mut map = Self::default();
+        for (remote, local) in mappings {
+            map.insert(remote, local, root)?;
+        }
+        Ok(map)
+    }
+
+    /// Adds a mapping, keeping an existing one for the same remote directory.
+    pub fn insert(&mut self, remote: &str, local: &str, root: &str) -> Result<(), String> {
+        let remote = remote.trim();
+        let local = local.trim();
+        if !remote.starts_with('/') {
+            return Err(format!("remote directory '{remote}' is not absolute"));
+        }
+        if local.is_empty() {
+            return Err(format!("no local directory for '{remote}'"));
+        }
+        if remote.contains([':', ',']) || local.contains([':', ',']) {
+            return Err(format!(
+                "'{remote}' and '{local}' cannot contain `:` or `,`, which separate mappings"
+            ));
+        }
+
+        let remote = normalize(remote);
+        if self
+            .mappings
+            .iter()
+            .any(|(existing, _)| *existing == remote)
+        {
+            return Ok(());
+        }
+        let local = if local.starts_with('/') {
+            normalize(local)
+        } else {
+            normalize(&format!("{}/{local}", root.trim_end_matches('/')))
+        };
+
+        let index = self
+            .mappings
+            .iter()
+            .position(|(existing, _)| existing.len() < remote.len())
+            .unwrap_or(self.mappings.len());
+        self.mappings.insert(index, (remote, local));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+}
+
+impl fmt::Display for LocalFsMap {
+    /// Formats the map as rdbg reads `localfsMap`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mappings: Vec<String> = self
+            .mappings
+            .iter()
+            .map(|(remote, local)| format!("{remote}:{local}"))
+            .collect();
+        write!(f, "{}", mappings.join(","))
+    }
+}
+
+/// Drops `.` components and trailing slashes, and resolves `..`.
+fn normalize(path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    format!("/{}", components.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_fs_map_from_rdbg_string() {
+        let map = LocalFsMap::new(
+            &LocalFsMapConfig::Rdbg("/app:/home/alice/shop, /gems:vendor/gems".to_string()),
+            "/home/alice/shop",
+        )
+        .unwrap();
+
+        assert_eq!(
+            map.to_string(),
+            "/gems:/home/alice/shop/vendor/gems,/app:/home/alice/shop"
+        );
+    }
+
+    #[test]
+    fn test_local_fs_map_from_paths() {
+        let map = LocalFsMap::new(
+            &LocalFsMapConfig::Paths(BTreeMap::from([
+                ("/app/".to_string(), ".".to_string()),
+                (
+                    "/app/engines/billing".to_string(),
+                    "../billing/".to_string(),
+                ),
+            ])),
+            "/home/alice/shop/",
+        )
+        .unwrap();
+
+        assert_eq!(
+            map.to_string(),
+            "/app/engines/billing:/home/alice/billing,/app:/home/alice/shop"
+        );
+    }
+
+    #[test]
+    fn test_local_fs_map_errors() {
+        let error = |map: &str| {
+            LocalFsMap::new(&LocalFsMapConfig::Rdbg(map.to_string()), "/root").unwrap_err()
+        };
+
+        assert_eq!(error(""), "no directories are mapped");
+        assert_eq!(
+            error("/app"),
+            "expected `remote:local` directories, got '/app'"
+        );
+        assert_eq!(error("app:/root"), "remote directory 'app' is not absolute");
+        assert_eq!(error("/app:"), "no local directory for '/app'");
+        assert_eq!(
+            error("/app:C:/code"),
+            "'/app' and 'C:/code' cannot contain `:` or `,`, which separate mappings"
+        );
+    }
+
+    #[test]
+    fn test_local_fs_map_config_serde() {
+        let config: LocalFsMapConfig = serde_json::from_str(r#""/app:.""#).unwrap();
+        assert_eq!(config, LocalFsMapConfig::Rdbg("/app:.".to_string()));
+
+        let config: LocalFsMapConfig = serde_json::from_str(r#"{"/app": "."}"#).unwrap();
+        assert_eq!(
+            config,
+            LocalFsMapConfig::Paths(BTreeMap::from([("/app".to_string(), ".".to_string())]))
+        );
+    }
+}
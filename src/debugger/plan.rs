@@ -0,0 +1,447 @@
+use crate::language_servers::GemfileLock;
+
+/// Executables whose gem is named differently.
+const EXECUTABLE_GEMS: &[(&str, &str)] = &[
+    ("rails", "railties"),
+    ("rspec", "rspec-core"),
+    ("rdbg", "debug"),
+];
+
+/// The program a launch request names.
+#[derive(Clone, Copy, Debug)]
+pub enum LaunchProgram<'a> {
+    /// A Ruby file, from `script`.
+    Script(&'a str),
+    /// An executable and its arguments, from `command`.
+    Command(&'a str),
+    /// Either of them, from `script_or_command`: a script when it ends with `.rb`.
+    ScriptOrCommand(&'a str),
+}
+
+/// How rdbg runs the program of a launch request.
+#[derive(Debug, PartialEq)]
+pub struct LaunchPlan {
+    /// Whether rdbg runs the program as a command, `rdbg --command`, rather
+    /// than loading it as a Ruby script.
+    pub is_command: bool,
+    /// The program and its arguments.
+    pub program: Vec<String>,
+}
+
+impl LaunchPlan {
+    /// Plans how to run `program` within the project's bundle:
+    ///
+    /// - commands that already start with `bundle` or a path run as written,
+    /// - commands with a binstub in `bin/` run the binstub,
+    /// - commands whose gem is in the `Gemfile.lock` run with `bundle exec`,
+    /// - other commands run as found on the `PATH`.
+    ///
+    /// Commands are split into words like a shell would, so quoted arguments
+    /// stay whole. A `script` is always a single path.
+    ///
+    /// When rdbg itself runs with `bundle exec`, the program inherits the
+    /// bundle and runs as written. Otherwise scripts run with
+    /// `bundle exec ruby` in projects with a `Gemfile.lock`, like `ruby`
+    /// commands. `has_file` tells whether a file exists relative to the
+    /// worktree `root`.
+    pub fn new(
+        program: LaunchProgram,
+        args: &[String],
+        lockfile: Option<&GemfileLock>,
+        rdbg_bundled: bool,
+        root: &str,
+        has_file: impl Fn(&str) -> bool,
+    ) -> Result<Self, String> {
+        let (is_script, words) = match program {
+            LaunchProgram::Script(script) => (true, vec![script.to_string()]),
+            LaunchProgram::Command(command) => (false, split_words(command)?),
+            LaunchProgram::ScriptOrCommand(program) => {
+                let words = split_words(program)?;
+                (
+                    words.first().is_some_and(|word| word.ends_with(".rb")),
+                    words,
+                )
+            }
+        };
+        let Some((executable, rest)) = words.split_first() else {
+            return Err("The program to launch is empty".to_string());
+        };
+        let executable = executable.as_str();
+        let in_bundle = lockfile.is_some() && !rdbg_bundled;
+
+        let mut program: Vec<String> = Vec::new();
+        let is_command = if is_script {
+            if in_bundle {
+                program.extend(["bundle", "exec", "ruby"].map(String::from));
+            }
+            program.push(executable.to_string());
+            in_bundle
+        } else {
+            let binstub = format!("bin/{executable}");
+            if executable == "bundle" || executable.contains('/') {
+                program.push(executable.to_string());
+            } else if has_file(&binstub) {
+                program.push(format!("{}/{binstub}", root.trim_end_matches('/')));
+            } else if in_bundle
+                && (executable == "ruby"
+                    || lockfile
+                        .is_some_and(|lockfile| lockfile.includes(executable_gem(executable))))
+            {
+                program.extend(["bundle", "exec", executable].map(String::from));
+            } else {
+                program.push(executable.to_string());
+            }
+            true
+        };
+        program.extend(rest.iter().cloned());
+        program.extend(args.iter().cloned());
+
+        Ok(Self {
+            is_command,
+            program,
+        })
+    }
+
+    /// The rdbg arguments that run the program, after its own options.
+    pub fn arguments(&self) -> Vec<String> {
+        let mut arguments = Vec::new();
+        if self.is_command {
+            arguments.push("--command".to_string());
+        }
+        arguments.push("--".to_string());
+        arguments.extend(self.program.iter().cloned());
+        arguments
+    }
+}
+
+/// Splits a command into words, honouring shell quotes and escapes.
+fn split_words(command: &str) -> Result<Vec<String>, String> {
+    shlex::split(command).ok_or_else(|| format!("Unbalanced quotes in `{command}`"))
+}
+
+/// The gem that provides an executable.
+fn executable_gem(executable: &str) -> &str {
+    EXECUTABLE_GEMS
+        .iter()
+        .find(|(name, _)| *name == executable)
+        .map_or(executable, |(_, gem)| gem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "/home/alice/shop";
+
+    const LOCKFILE: &str = "\
+GEM
+  remote: https://rubygems.org/
+  specs:
+    railties (7.1.3)
+      rake (>= 12.2)
+    rake (13.1.0)
+    rspec-core (3.13.0)
+    rspec-rails (6.1.1)
+      rspec-core (~> 3.13)
+
+DEPENDENCIES
+  rails
+  rspec-rails
+";
+
+    fn plan(
+        program: LaunchProgram,
+        args: &[&str],
+        lockfile: Option<&str>,
+        rdbg_bundled: bool,
+        binstubs: &[&str],
+    ) -> Vec<String> {
+        let lockfile = lockfile.map(GemfileLock::parse);
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        LaunchPlan::new(
+            program,
+            &args,
+            lockfile.as_ref(),
+            rdbg_bundled,
+            ROOT,
+            |path| binstubs.contains(&path),
+        )
+        .unwrap()
+        .arguments()
+    }
+
+    #[test]
+    fn test_plan_script() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Script("app.rb"),
+                &["--verbose"],
+                None,
+                false,
+                &[]
+            ),
+            ["--", "app.rb", "--verbose"]
+        );
+        assert_eq!(
+            plan(
+                LaunchProgram::Script("app.rb"),
+                &[],
+                Some(LOCKFILE),
+                true,
+                &[]
+            ),
+            ["--", "app.rb"]
+        );
+        assert_eq!(
+            plan(
+                LaunchProgram::Script("app.rb"),
+                &[],
+                Some(LOCKFILE),
+                false,
+                &[]
+            ),
+            ["--command", "--", "bundle", "exec", "ruby", "app.rb"]
+        );
+    }
+
+    #[test]
+    fn test_plan_script_or_command() {
+        assert_eq!(
+            plan(
+                LaunchProgram::ScriptOrCommand("test/models/user_test.rb --seed 1"),
+                &[],
+                None,
+                false,
+                &[]
+            ),
+            ["--", "test/models/user_test.rb", "--seed", "1"]
+        );
+        assert_eq!(
+            plan(
+                LaunchProgram::ScriptOrCommand("rake"),
+                &["db:seed"],
+                None,
+                false,
+                &[]
+            ),
+            ["--command", "--", "rake", "db:seed"]
+        );
+    }
+
+    #[test]
+    fn test_plan_binstub() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("rails"),
+                &["server"],
+                Some(LOCKFILE),
+                false,
+                &["bin/rails"]
+            ),
+            ["--command", "--", "/home/alice/shop/bin/rails", "server"]
+        );
+    }
+
+    #[test]
+    fn test_plan_gem_in_bundle() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("rspec spec/models"),
+                &["--fail-fast"],
+                Some(LOCKFILE),
+                false,
+                &[]
+            ),
+            [
+                "--command",
+                "--",
+                "bundle",
+                "exec",
+                "rspec",
+                "spec/models",
+                "--fail-fast"
+            ]
+        );
+        // Locked as a dependency of another gem.
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("rake"),
+                &[],
+                Some(LOCKFILE),
+                false,
+                &[]
+            ),
+            ["--command", "--", "bundle", "exec", "rake"]
+        );
+    }
+
+    #[test]
+    fn test_plan_gem_with_bundled_rdbg() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("rake"),
+                &[],
+                Some(LOCKFILE),
+                true,
+                &[]
+            ),
+            ["--command", "--", "rake"]
+        );
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("rspec spec/models"),
+                &[],
+                Some(LOCKFILE),
+                true,
+                &[]
+            ),
+            ["--command", "--", "rspec", "spec/models"]
+        );
+    }
+
+    #[test]
+    fn test_plan_gem_outside_bundle() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("rubocop"),
+                &[],
+                Some(LOCKFILE),
+                false,
+                &[]
+            ),
+            ["--command", "--", "rubocop"]
+        );
+        assert_eq!(
+            plan(LaunchProgram::Command("rspec"), &[], None, false, &[]),
+            ["--command", "--", "rspec"]
+        );
+    }
+
+    #[test]
+    fn test_plan_ruby_command() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("ruby -Itest test/a_test.rb"),
+                &[],
+                Some(LOCKFILE),
+                false,
+                &[]
+            ),
+            [
+                "--command",
+                "--",
+                "bundle",
+                "exec",
+                "ruby",
+                "-Itest",
+                "test/a_test.rb"
+            ]
+        );
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("ruby app.rb"),
+                &[],
+                Some(LOCKFILE),
+                true,
+                &[]
+            ),
+            ["--command", "--", "ruby", "app.rb"]
+        );
+    }
+
+    #[test]
+    fn test_plan_keeps_bundle_exec_and_paths() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("bundle exec rspec"),
+                &[],
+                Some(LOCKFILE),
+                false,
+                &["bin/rspec"]
+            ),
+            ["--command", "--", "bundle", "exec", "rspec"]
+        );
+        assert_eq!(
+            plan(
+                LaunchProgram::Command("bin/rails test"),
+                &[],
+                Some(LOCKFILE),
+                false,
+                &[]
+            ),
+            ["--command", "--", "bin/rails", "test"]
+        );
+    }
+
+    #[test]
+    fn test_plan_quoted_arguments() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Command(r#"rspec -e "signs in" spec/system"#),
+                &[],
+                Some(LOCKFILE),
+                false,
+                &[]
+            ),
+            [
+                "--command",
+                "--",
+                "bundle",
+                "exec",
+                "rspec",
+                "-e",
+                "signs in",
+                "spec/system"
+            ]
+        );
+        assert_eq!(
+            plan(
+                LaunchProgram::ScriptOrCommand("'my dir/app.rb' --name 'Jane Doe'"),
+                &[],
+                None,
+                false,
+                &[]
+            ),
+            ["--", "my dir/app.rb", "--name", "Jane Doe"]
+        );
+    }
+
+    #[test]
+    fn test_plan_script_with_spaces() {
+        assert_eq!(
+            plan(
+                LaunchProgram::Script("my dir/app.rb"),
+                &["--verbose"],
+                None,
+                false,
+                &[]
+            ),
+            ["--", "my dir/app.rb", "--verbose"]
+        );
+    }
+
+    #[test]
+    fn test_plan_unbalanced_quotes() {
+        assert_eq!(
+            LaunchPlan::new(
+                LaunchProgram::Command(r#"rspec -e "signs in"#),
+                &[],
+                None,
+                false,
+                ROOT,
+                |_| false
+            ),
+            Err(r#"Unbalanced quotes in `rspec -e "signs in`"#.to_string())
+        );
+    }
+
+    #[test]
+    fn test_plan_empty_program() {
+        assert_eq!(
+            LaunchPlan::new(LaunchProgram::Command("  "), &[], None, false, ROOT, |_| {
+                false
+            }),
+            Err("The program to launch is empty".to_string())
+        );
+    }
+}
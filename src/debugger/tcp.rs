@@ -0,0 +1,395 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr},
+    str::FromStr,
+};
+
+use super::RubyDebugConfig;
+
+/// A host rdbg listens on, or attaches to: an IPv4 or IPv6 address, or a
+/// hostname such as `localhost` or a docker-compose service like `web`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DebugHost {
+    Ip(IpAddr),
+    Name(String),
+}
+
+impl FromStr for DebugHost {
+    type Err = String;
+
+    fn from_str(host: &str) -> Result<Self, Self::Err> {
+        let host = host.trim();
+        let address = host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host);
+        if let Ok(ip) = address.parse::<IpAddr>() {
+            return Ok(Self::Ip(ip));
+        }
+
+        let name = host.strip_suffix('.').unwrap_or(host);
+        let is_label = |label: &str| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        if name.split('.').all(is_label) {
+            return Ok(Self::Name(name.to_string()));
+        }
+
+        Err(format!("'{host}' is not an IP address or a hostname"))
+    }
+}
+
+impl fmt::Display for DebugHost {
+    /// Formats the host as rdbg's `--host` takes it, IPv6 without brackets.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "{ip}"),
+            Self::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl DebugHost {
+    /// The address Zed connects to, or `None` when Zed cannot reach the host
+    /// itself. Zed only connects to debug adapters over IPv4 and cannot
+    /// resolve hostnames, so unspecified addresses and `localhost`, which
+    /// rdbg also listens on over IPv4, become `127.0.0.1`.
+    pub fn connect_address(&self) -> Option<Ipv4Addr> {
+        match self {
+            Self::Ip(IpAddr::V4(ip)) if ip.is_unspecified() => Some(Ipv4Addr::LOCALHOST),
+            Self::Ip(IpAddr::V4(ip)) => Some(*ip),
+            Self::Ip(IpAddr::V6(ip)) if ip.is_unspecified() => Some(Ipv4Addr::LOCALHOST),
+            Self::Ip(IpAddr::V6(ip)) => ip.to_ipv4_mapped(),
+            Self::Name(name) if name.eq_ignore_ascii_case("localhost") => Some(Ipv4Addr::LOCALHOST),
+            Self::Name(_) => None,
+        }
+    }
+}
+
+/// Where a debug session connects over TCP.
+///
+/// Each of the host and port comes from the first of the `host`/`port`
+/// config fields, the `RUBY_DEBUG_HOST`/`RUBY_DEBUG_PORT` variables and the
+/// scenario's `tcp_connection`. Without any, Zed picks `127.0.0.1` and a free
+/// port.
+#[derive(Debug, Default, PartialEq)]
+pub struct TcpTarget {
+    /// The host given to rdbg, `None` when rdbg reads it from `RUBY_DEBUG_HOST`.
+    pub host: Option<DebugHost>,
+    /// The address Zed connects to, `None` for the default.
+    pub address: Option<Ipv4Addr>,
+    /// A host Zed cannot connect to itself, such as a hostname or an IPv6
+    /// address, which an attach request reaches through a
+    /// [`Bridge`](super::Bridge).
+    pub remote: Option<DebugHost>,
+    pub port: Option<u16>,
+    /// Whether rdbg reads the port from `RUBY_DEBUG_PORT` itself.
+    pub port_from_env: bool,
+}
+
+impl TcpTarget {
+    pub fn resolve(
+        config: &RubyDebugConfig,
+        debug_env: &HashMap<String, String>,
+        template_host: Option<Ipv4Addr>,
+        template_port: Option<u16>,
+    ) -> Result<Self, String> {
+        let mut target = Self::default();
+
+        if let Some(host) = &config.host {
+            let host: DebugHost = host.parse().map_err(|e| format!("Invalid `host`: {e}"))?;
+            target.connect_to(&host);
+            target.host = Some(host);
+        } else if let Some(host) = debug_env.get("RUBY_DEBUG_HOST") {
+            let host: DebugHost = host
+                .parse()
+                .map_err(|e| format!("Invalid RUBY_DEBUG_HOST: {e}"))?;
+            target.connect_to(&host);
+        } else {
+            target.host = Some(DebugHost::Ip(
+                template_host.unwrap_or(Ipv4Addr::LOCALHOST).into(),
+            ));
+            target.address = template_host;
+        }
+
+        if let Some(port) = config.port {
+            target.port = Some(valid_port(port).ok_or("Invalid `port`: 0 is not a port")?);
+        } else if let Some(port) = debug_env.get("RUBY_DEBUG_PORT") {
+            target.port = Some(
+                port.parse::<u16>()
+                    .ok()
+                    .and_then(valid_port)
+                    .ok_or_else(|| {
+                        format!("Invalid port number '{port}' specified via RUBY_DEBUG_PORT")
+                    })?,
+            );
+            target.port_from_env = true;
+        } else {
+            target.port = template_port;
+        }
+
+        Ok(target)
+    }
+
+    fn connect_to(&mut self, host: &DebugHost) {
+        match host.connect_address() {
+            Some(address) => self.address = Some(address),
+            None => self.remote = Some(host.clone()),
+        }
+    }
+
+    /// Fails for a launch request on a host Zed cannot connect to, as rdbg
+    /// then listens where Zed cannot reach it.
+    pub fn ensure_reachable(&self) -> Result<(), String> {
+        match &self.remote {
+            Some(host) => Err(format!(
+                "Zed cannot connect to rdbg listening on {host}: it connects to debug \
+                 adapters over IPv4 only and cannot resolve hostnames. Launch on an \
+                 IPv4 address, localhost or ::"
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The `--host` and `--port` arguments for rdbg, leaving out what it
+    /// reads from its environment. `port` is the one Zed settled on.
+    pub fn arguments(&self, port: u16) -> Vec<String> {
+        let mut arguments = Vec::new();
+        if let Some(host) = &self.host {
+            arguments.push(format!("--host={host}"));
+        }
+        if !self.port_from_env {
+            arguments.push(format!("--port={port}"));
+        }
+        arguments
+    }
+}
+
+fn valid_port(port: u16) -> Option<u16> {
+    (port != 0).then_some(port)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use super::*;
+
+    fn config(host: Option<&str>, port: Option<u16>) -> RubyDebugConfig {
+        RubyDebugConfig {
+            host: host.map(str::to_string),
+            port,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_debug_host() {
+        assert_eq!(
+            "10.0.0.5".parse(),
+            Ok(DebugHost::Ip(Ipv4Addr::new(10, 0, 0, 5).into()))
+        );
+        assert_eq!("::1".parse(), Ok(DebugHost::Ip(Ipv6Addr::LOCALHOST.into())));
+        assert_eq!(
+            "[::1]".parse(),
+            Ok(DebugHost::Ip(Ipv6Addr::LOCALHOST.into()))
+        );
+        assert_eq!(
+            "LocalHost.".parse(),
+            Ok(DebugHost::Name("LocalHost".to_string()))
+        );
+        assert_eq!("web".parse(), Ok(DebugHost::Name("web".to_string())));
+        assert_eq!(
+            "db-1.staging_net.example.com".parse(),
+            Ok(DebugHost::Name("db-1.staging_net.example.com".to_string()))
+        );
+        assert_eq!(
+            "web server".parse::<DebugHost>(),
+            Err("'web server' is not an IP address or a hostname".to_string())
+        );
+        assert!("".parse::<DebugHost>().is_err());
+        assert!("web..local".parse::<DebugHost>().is_err());
+        assert!("-web".parse::<DebugHost>().is_err());
+    }
+
+    #[test]
+    fn test_debug_host_display() {
+        let host: DebugHost = "[::1]".parse().unwrap();
+        assert_eq!(host.to_string(), "::1");
+        let host: DebugHost = "web.".parse().unwrap();
+        assert_eq!(host.to_string(), "web");
+    }
+
+    #[test]
+    fn test_connect_address() {
+        let address = |host: &str| host.parse::<DebugHost>().unwrap().connect_address();
+
+        assert_eq!(address("10.0.0.5"), Some(Ipv4Addr::new(10, 0, 0, 5)));
+        assert_eq!(address("0.0.0.0"), Some(Ipv4Addr::LOCALHOST));
+        assert_eq!(address("::"), Some(Ipv4Addr::LOCALHOST));
+        assert_eq!(address("::ffff:10.0.0.5"), Some(Ipv4Addr::new(10, 0, 0, 5)));
+        assert_eq!(address("localhost"), Some(Ipv4Addr::LOCALHOST));
+        assert_eq!(address("::1"), None);
+        assert_eq!(address("fd00::5"), None);
+        assert_eq!(address("web"), None);
+    }
+
+    /// Every combination of where the host and port come from: the config
+    /// fields, the environment, the scenario's `tcp_connection`, or nowhere.
+    #[test]
+    fn test_tcp_target_precedence() {
+        let config_host = Some("192.168.1.20");
+        let env_host = Some("::");
+        let template_host = Some(Ipv4Addr::new(10, 0, 0, 9));
+        let config_port = Some(3000);
+        let env_port = Some("4000");
+        let template_port = Some(5000);
+
+        let cases = [
+            // (config host, env host, template host) -> (host argument, address)
+            (
+                (config_host, env_host, template_host),
+                (Some("192.168.1.20"), Some([192, 168, 1, 20])),
+            ),
+            (
+                (config_host, None, None),
+                (Some("192.168.1.20"), Some([192, 168, 1, 20])),
+            ),
+            (
+                (None, env_host, template_host),
+                (None, Some([127, 0, 0, 1])),
+            ),
+            ((None, env_host, None), (None, Some([127, 0, 0, 1]))),
+            (
+                (None, None, template_host),
+                (Some("10.0.0.9"), Some([10, 0, 0, 9])),
+            ),
+            ((None, None, None), (Some("127.0.0.1"), None)),
+        ];
+        for ((config_host, env_host, template_host), (host, address)) in cases {
+            let mut debug_env = HashMap::new();
+            if let Some(env_host) = env_host {
+                debug_env.insert("RUBY_DEBUG_HOST".to_string(), env_host.to_string());
+            }
+            let target =
+                TcpTarget::resolve(&config(config_host, None), &debug_env, template_host, None)
+                    .unwrap();
+
+            let case = format!("host {config_host:?}, {env_host:?}, {template_host:?}");
+            assert_eq!(
+                target.host.map(|host| host.to_string()).as_deref(),
+                host,
+                "{case}"
+            );
+            assert_eq!(target.address, address.map(Ipv4Addr::from), "{case}");
+        }
+
+        let cases = [
+            // (config port, env port, template port) -> (port, port from env)
+            ((config_port, env_port, template_port), (Some(3000), false)),
+            ((config_port, None, None), (Some(3000), false)),
+            ((None, env_port, template_port), (Some(4000), true)),
+            ((None, env_port, None), (Some(4000), true)),
+            ((None, None, template_port), (Some(5000), false)),
+            ((None, None, None), (None, false)),
+        ];
+        for ((config_port, env_port, template_port), (port, port_from_env)) in cases {
+            let mut debug_env = HashMap::new();
+            if let Some(env_port) = env_port {
+                debug_env.insert("RUBY_DEBUG_PORT".to_string(), env_port.to_string());
+            }
+            let target =
+                TcpTarget::resolve(&config(None, config_port), &debug_env, None, template_port)
+                    .unwrap();
+
+            let case = format!("port {config_port:?}, {env_port:?}, {template_port:?}");
+            assert_eq!(target.port, port, "{case}");
+            assert_eq!(target.port_from_env, port_from_env, "{case}");
+        }
+    }
+
+    #[test]
+    fn test_tcp_target_arguments() {
+        let target =
+            TcpTarget::resolve(&config(Some("::"), Some(3000)), &HashMap::new(), None, None)
+                .unwrap();
+        assert_eq!(target.address, Some(Ipv4Addr::LOCALHOST));
+        assert_eq!(target.arguments(3000), vec!["--host=::", "--port=3000"]);
+
+        let debug_env = HashMap::from([
+            ("RUBY_DEBUG_HOST".to_string(), "10.0.0.5".to_string()),
+            ("RUBY_DEBUG_PORT".to_string(), "12345".to_string()),
+        ]);
+        let target = TcpTarget::resolve(&config(None, None), &debug_env, None, None).unwrap();
+        assert_eq!(target.address, Some(Ipv4Addr::new(10, 0, 0, 5)));
+        assert!(target.arguments(12345).is_empty());
+    }
+
+    #[test]
+    fn test_tcp_target_remote_host() {
+        let target = TcpTarget::resolve(
+            &config(Some("web"), Some(3000)),
+            &HashMap::new(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(target.address, None);
+        assert_eq!(target.remote, Some(DebugHost::Name("web".to_string())));
+        assert_eq!(target.arguments(3000), vec!["--host=web", "--port=3000"]);
+        assert_eq!(
+            target.ensure_reachable(),
+            Err(
+                "Zed cannot connect to rdbg listening on web: it connects to debug \
+                 adapters over IPv4 only and cannot resolve hostnames. Launch on an \
+                 IPv4 address, localhost or ::"
+                    .to_string()
+            )
+        );
+
+        let debug_env = HashMap::from([("RUBY_DEBUG_HOST".to_string(), "::1".to_string())]);
+        let target = TcpTarget::resolve(&config(None, None), &debug_env, None, None).unwrap();
+        assert_eq!(
+            target.remote,
+            Some(DebugHost::Ip(Ipv6Addr::LOCALHOST.into()))
+        );
+        assert_eq!(target.host, None);
+
+        let target =
+            TcpTarget::resolve(&config(Some("::"), None), &HashMap::new(), None, None).unwrap();
+        assert_eq!(target.ensure_reachable(), Ok(()));
+    }
+
+    #[test]
+    fn test_tcp_target_errors() {
+        let resolve = |host: Option<&str>, port: Option<u16>, env: &[(&str, &str)]| {
+            let debug_env = env
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            TcpTarget::resolve(&config(host, port), &debug_env, None, None).unwrap_err()
+        };
+
+        assert_eq!(
+            resolve(Some("web server"), None, &[]),
+            "Invalid `host`: 'web server' is not an IP address or a hostname"
+        );
+        assert_eq!(
+            resolve(None, None, &[("RUBY_DEBUG_HOST", "web/1")]),
+            "Invalid RUBY_DEBUG_HOST: 'web/1' is not an IP address or a hostname"
+        );
+        assert_eq!(
+            resolve(None, Some(0), &[]),
+            "Invalid `port`: 0 is not a port"
+        );
+        assert_eq!(
+            resolve(None, None, &[("RUBY_DEBUG_PORT", "http")]),
+            "Invalid port number 'http' specified via RUBY_DEBUG_PORT"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::command_executor::CommandExecutor;
+use crate::version::RubyInterpreter;
 use anyhow::{anyhow, bail, Context, Result};
 use regex::Regex;
 use std::{
@@ -8,27 +9,13 @@ use std::{
     sync::{LazyLock, OnceLock},
 };
 
-pub fn versioned_gem_home(
-    base_dir: &Path,
-    envs: &[(&str, &str)],
-    executor: &dyn CommandExecutor,
-) -> Result<PathBuf> {
-    let output = executor
-        .execute("ruby", &["--version"], envs)
-        .map_err(|e| anyhow::anyhow!(e))
-        .context("Failed to detect Ruby version")?;
-
-    match output.status {
-        Some(0) => {
-            let version_string = String::from_utf8_lossy(&output.stdout);
-            let mut hasher = DefaultHasher::new();
-            version_string.trim().hash(&mut hasher);
-            let version_hash = format!("{:x}", hasher.finish());
-            Ok(base_dir.join("gems").join(version_hash))
-        }
-        Some(status) => bail!("Ruby version check failed with status {status}"),
-        None => bail!("Failed to execute ruby --version"),
-    }
+/// The gem home under `base_dir` for gems installed with `ruby`, which differs
+/// per interpreter because native extensions are built for one.
+pub fn interpreter_gem_home(base_dir: &Path, ruby: &RubyInterpreter) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    ruby.description().hash(&mut hasher);
+    let version_hash = format!("{:x}", hasher.finish());
+    base_dir.join("gems").join(version_hash)
 }
 
 /// A simple wrapper around the `gem` command.
@@ -286,8 +273,7 @@ mod tests {
         Gemset::new(TEST_GEM_HOME.into(), envs, Box::new(mock_executor))
     }
 
-    #[test]
-    fn test_versioned_gem_home_success() {
+    fn gem_home(version_output: &str) -> PathBuf {
         let executor = MockCommandExecutor::new();
         executor.expect(
             "ruby",
@@ -295,128 +281,32 @@ mod tests {
             &[],
             Ok(Output {
                 status: Some(0),
-                stdout: "ruby 3.3.0 (2023-12-25 revision 5124f9ac75) [arm64-darwin23]\n"
-                    .as_bytes()
-                    .to_vec(),
+                stdout: version_output.as_bytes().to_vec(),
                 stderr: Vec::new(),
             }),
         );
+        let ruby = RubyInterpreter::detect(&[], &executor).expect("ruby should run");
+        interpreter_gem_home(Path::new("/extension"), &ruby)
+    }
 
-        let result = versioned_gem_home(Path::new("/extension"), &[], &executor);
-        assert!(result.is_ok());
-        let path = result.expect("should return path");
+    #[test]
+    fn test_interpreter_gem_home() {
+        let path = gem_home("ruby 3.3.0 (2023-12-25 revision 5124f9ac75) [arm64-darwin23]\n");
         assert!(path.starts_with("/extension/gems/"));
         assert_eq!(path.components().count(), 4);
     }
 
     #[test]
-    fn test_versioned_gem_home_different_versions_produce_different_hashes() {
-        let executor1 = MockCommandExecutor::new();
-        executor1.expect(
-            "ruby",
-            &["--version"],
-            &[],
-            Ok(Output {
-                status: Some(0),
-                stdout: "ruby 3.3.0 (2023-12-25 revision 5124f9ac75) [arm64-darwin23]\n"
-                    .as_bytes()
-                    .to_vec(),
-                stderr: Vec::new(),
-            }),
-        );
-
-        let executor2 = MockCommandExecutor::new();
-        executor2.expect(
-            "ruby",
-            &["--version"],
-            &[],
-            Ok(Output {
-                status: Some(0),
-                stdout: "ruby 3.2.2 (2023-03-30 revision e51014f9c0) [arm64-darwin23]\n"
-                    .as_bytes()
-                    .to_vec(),
-                stderr: Vec::new(),
-            }),
-        );
-
-        let path1 = versioned_gem_home(Path::new("/extension"), &[], &executor1)
-            .expect("should return path");
-        let path2 = versioned_gem_home(Path::new("/extension"), &[], &executor2)
-            .expect("should return path");
-
+    fn test_interpreter_gem_home_different_versions_produce_different_hashes() {
+        let path1 = gem_home("ruby 3.3.0 (2023-12-25 revision 5124f9ac75) [arm64-darwin23]\n");
+        let path2 = gem_home("ruby 3.2.2 (2023-03-30 revision e51014f9c0) [arm64-darwin23]\n");
         assert_ne!(path1, path2);
     }
 
     #[test]
-    fn test_versioned_gem_home_same_version_produces_same_hash() {
+    fn test_interpreter_gem_home_same_version_produces_same_hash() {
         let version_output = "ruby 3.3.0 (2023-12-25 revision 5124f9ac75) [arm64-darwin23]\n";
-
-        let executor1 = MockCommandExecutor::new();
-        executor1.expect(
-            "ruby",
-            &["--version"],
-            &[],
-            Ok(Output {
-                status: Some(0),
-                stdout: version_output.as_bytes().to_vec(),
-                stderr: Vec::new(),
-            }),
-        );
-
-        let executor2 = MockCommandExecutor::new();
-        executor2.expect(
-            "ruby",
-            &["--version"],
-            &[],
-            Ok(Output {
-                status: Some(0),
-                stdout: version_output.as_bytes().to_vec(),
-                stderr: Vec::new(),
-            }),
-        );
-
-        let path1 = versioned_gem_home(Path::new("/extension"), &[], &executor1)
-            .expect("should return path");
-        let path2 = versioned_gem_home(Path::new("/extension"), &[], &executor2)
-            .expect("should return path");
-
-        assert_eq!(path1, path2);
-    }
-
-    #[test]
-    fn test_versioned_gem_home_command_failure() {
-        let executor = MockCommandExecutor::new();
-        executor.expect(
-            "ruby",
-            &["--version"],
-            &[],
-            Ok(Output {
-                status: Some(127),
-                stdout: Vec::new(),
-                stderr: "ruby: command not found".as_bytes().to_vec(),
-            }),
-        );
-
-        let result = versioned_gem_home(Path::new("/extension"), &[], &executor);
-        assert!(result.is_err());
-        let error_message = format!("{:#}", result.expect_err("should return error"));
-        assert!(error_message.contains("Ruby version check failed with status 127"));
-    }
-
-    #[test]
-    fn test_versioned_gem_home_execution_error() {
-        let executor = MockCommandExecutor::new();
-        executor.expect(
-            "ruby",
-            &["--version"],
-            &[],
-            Err("Failed to spawn process".to_string()),
-        );
-
-        let result = versioned_gem_home(Path::new("/extension"), &[], &executor);
-        assert!(result.is_err());
-        let error_message = format!("{:#}", result.expect_err("should return error"));
-        assert!(error_message.contains("Failed to detect Ruby version"));
+        assert_eq!(gem_home(version_output), gem_home(version_output));
     }
 
     #[test]
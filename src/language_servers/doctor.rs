@@ -0,0 +1,188 @@
+use zed_extension_api::{self as zed};
+
+use super::{
+    language_server::WorktreeLike, FuzzyRubyServer, Herb, Kanayago, LanguageServer, Rubocop,
+    RubyLsp, Solargraph, Sorbet, Standard, Steep, SyntaxTree, TypeProf,
+};
+
+/// The name of the slash command that shows the report.
+pub const DOCTOR_COMMAND: &str = "ruby-doctor";
+
+/// One language server's part of the `/ruby-doctor` report.
+#[derive(Debug, PartialEq)]
+pub struct DoctorSection {
+    pub title: &'static str,
+    pub lines: Vec<String>,
+}
+
+/// What the extension inferred for the worktree, grouped by language server,
+/// followed by the settings it ignored. Steep is described as it last
+/// started, when it did.
+pub fn doctor_report<T: WorktreeLike>(worktree: &T, steep: Option<&Steep>) -> Vec<DoctorSection> {
+    vec![
+        DoctorSection {
+            title: "Ruby LSP",
+            lines: RubyLsp::new().doctor_report(RubyLsp::SERVER_ID, worktree),
+        },
+        DoctorSection {
+            title: "Steep",
+            lines: match steep {
+                Some(steep) => steep.doctor_report(),
+                None => vec!["Not started in this session".to_string()],
+            },
+        },
+        DoctorSection {
+            title: "Settings",
+            lines: settings_warnings(worktree),
+        },
+    ]
+}
+
+/// The ignored or unreadable `lsp.<id>.settings` of every server.
+fn settings_warnings<T: WorktreeLike>(worktree: &T) -> Vec<String> {
+    [
+        FuzzyRubyServer::settings_warnings(FuzzyRubyServer::SERVER_ID, worktree),
+        Herb::settings_warnings(Herb::SERVER_ID, worktree),
+        Kanayago::settings_warnings(Kanayago::SERVER_ID, worktree),
+        Rubocop::settings_warnings(Rubocop::SERVER_ID, worktree),
+        RubyLsp::settings_warnings(RubyLsp::SERVER_ID, worktree),
+        Solargraph::settings_warnings(Solargraph::SERVER_ID, worktree),
+        Sorbet::settings_warnings(Sorbet::SERVER_ID, worktree),
+        Standard::settings_warnings(Standard::SERVER_ID, worktree),
+        Steep::settings_warnings(Steep::SERVER_ID, worktree),
+        SyntaxTree::settings_warnings(SyntaxTree::SERVER_ID, worktree),
+        TypeProf::settings_warnings(TypeProf::SERVER_ID, worktree),
+    ]
+    .concat()
+}
+
+/// Renders the report as Markdown, folding each server into its own section.
+pub fn doctor_output(sections: Vec<DoctorSection>) -> zed::SlashCommandOutput {
+    let mut text = String::new();
+    let mut output_sections = Vec::new();
+
+    for section in sections {
+        let start = text.len();
+        text.push_str(&format!("## {}\n\n", section.title));
+        if section.lines.is_empty() {
+            text.push_str("Nothing to report.\n");
+        }
+        for line in &section.lines {
+            text.push_str(&format!("- {line}\n"));
+        }
+        output_sections.push(zed::SlashCommandOutputSection {
+            range: zed::Range {
+                start: start as u32,
+                end: text.len() as u32,
+            },
+            label: section.title.to_string(),
+        });
+        text.push('\n');
+    }
+
+    zed::SlashCommandOutput {
+        text,
+        sections: output_sections,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language_servers::{language_server::FakeWorktree, project::GEMFILE_LOCK};
+    use zed_extension_api::serde_json::json;
+
+    #[test]
+    fn test_doctor_report() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(
+            GEMFILE_LOCK.to_string(),
+            Ok("DEPENDENCIES\n  rspec\n  standard\n".to_string()),
+        );
+
+        assert_eq!(
+            doctor_report(&worktree, None),
+            vec![
+                DoctorSection {
+                    title: "Ruby LSP",
+                    lines: vec![
+                        "`formatter`: `\"standard\"`, inferred from the project".to_string(),
+                        "`linters`: `[\"standard\"]`, inferred from the project".to_string(),
+                        "`testLibrary`: `\"rspec\"`, inferred from the project".to_string(),
+                    ],
+                },
+                DoctorSection {
+                    title: "Steep",
+                    lines: vec!["Not started in this session".to_string()],
+                },
+                DoctorSection {
+                    title: "Settings",
+                    lines: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doctor_report_lists_ignored_settings() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_server_settings(
+            Rubocop::SERVER_ID.to_string(),
+            json!({ "useBundler": false, "require_config": "yes" }),
+        );
+        worktree.set_server_settings(Sorbet::SERVER_ID.to_string(), json!([]));
+
+        let settings = doctor_report(&worktree, None)
+            .pop()
+            .expect("the report ends with the settings");
+
+        assert_eq!(settings.title, "Settings");
+        assert_eq!(settings.lines.len(), 3, "{:?}", settings.lines);
+        assert_eq!(
+            settings.lines[0],
+            "lsp.rubocop.settings.require_config: invalid type: string \"yes\", expected a boolean"
+        );
+        assert_eq!(
+            settings.lines[1],
+            "lsp.rubocop.settings.useBundler is not a known setting, did you mean use_bundler?"
+        );
+        assert_eq!(settings.lines[2], "lsp.sorbet.settings must be an object");
+    }
+
+    #[test]
+    fn test_doctor_output() {
+        let output = doctor_output(vec![
+            DoctorSection {
+                title: "Ruby LSP",
+                lines: vec!["`formatter`: `\"rubocop\"`".to_string()],
+            },
+            DoctorSection {
+                title: "Steep",
+                lines: Vec::new(),
+            },
+        ]);
+
+        assert_eq!(
+            output.text,
+            "## Ruby LSP\n\n- `formatter`: `\"rubocop\"`\n\n## Steep\n\nNothing to report.\n\n"
+        );
+        let ranges: Vec<(String, &str)> = output
+            .sections
+            .iter()
+            .map(|section| {
+                let range = section.range.start as usize..section.range.end as usize;
+                (section.label.clone(), &output.text[range])
+            })
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (
+                    "Ruby LSP".to_string(),
+                    "## Ruby LSP\n\n- `formatter`: `\"rubocop\"`\n"
+                ),
+                ("Steep".to_string(), "## Steep\n\nNothing to report.\n"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::language_server::WorktreeLike;
+
+const GEMFILES: &[&str] = &["Gemfile", "gems.rb"];
+
+/// The tool that puts the project's `ruby` on `PATH`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RubyVersionManager {
+    Asdf,
+    Chruby,
+    Mise,
+    Rbenv,
+    Rvm,
+    Shadowenv,
+    None,
+}
+
+impl RubyVersionManager {
+    /// Detects the manager from the worktree shell environment and the `ruby`
+    /// found on its `PATH`, which is the interpreter the extension runs.
+    pub fn detect<T: WorktreeLike>(worktree: &T) -> Option<Self> {
+        if worktree
+            .shell_env()
+            .iter()
+            .any(|(key, _)| key == "__shadowenv_data")
+        {
+            return Some(Self::Shadowenv);
+        }
+
+        let ruby = worktree.which("ruby")?;
+        let manager = if ruby.contains("/.rbenv/") {
+            Self::Rbenv
+        } else if ruby.contains("/.asdf/") {
+            Self::Asdf
+        } else if ruby.contains("/mise/") {
+            Self::Mise
+        } else if ruby.contains("/.rvm/") {
+            Self::Rvm
+        } else if ruby.contains("/.rubies/") || ruby.contains("/opt/rubies/") {
+            Self::Chruby
+        } else {
+            Self::None
+        };
+
+        Some(manager)
+    }
+
+    /// The identifier Ruby LSP uses for this manager.
+    pub fn identifier(&self) -> &'static str {
+        match self {
+            Self::Asdf => "asdf",
+            Self::Chruby => "chruby",
+            Self::Mise => "mise",
+            Self::Rbenv => "rbenv",
+            Self::Rvm => "rvm",
+            Self::Shadowenv => "shadowenv",
+            Self::None => "none",
+        }
+    }
+}
+
+/// Returns the Gemfile Bundler uses when the extension runs it in the worktree
+/// root: `BUNDLE_GEMFILE` from the shell environment, otherwise the root
+/// `Gemfile` or `gems.rb`.
+pub fn bundle_gemfile<T: WorktreeLike>(worktree: &T) -> Option<String> {
+    let root_path = worktree.root_path();
+
+    if let Some((_, gemfile)) = worktree
+        .shell_env()
+        .into_iter()
+        .find(|(key, value)| key == "BUNDLE_GEMFILE" && !value.is_empty())
+    {
+        return Some(Path::new(&root_path).join(gemfile).display().to_string());
+    }
+
+    GEMFILES
+        .iter()
+        .find(|gemfile| worktree.read_text_file(gemfile).is_ok())
+        .map(|gemfile| Path::new(&root_path).join(gemfile).display().to_string())
+}
+
+/// Variables set on top of the worktree shell environment for one process.
+///
+/// The `env_file` is applied first and the explicit `env` map last, so a
+/// setting wins over the file. An empty value in the map removes the variable,
+/// since Zed settings cannot express `null`; in the file, `KEY=` follows dotenv
+/// and sets an empty value.
+#[derive(Debug, Default, PartialEq)]
+pub struct EnvOverrides {
+    vars: Vec<(String, Option<String>)>,
+}
+
+impl EnvOverrides {
+    /// Reads `env_file`, relative to the worktree root, and merges `env` over it.
+    pub fn load<T: WorktreeLike>(
+        worktree: &T,
+        env_file: Option<&str>,
+        env: Option<&HashMap<String, String>>,
+    ) -> Result<Self, String> {
+        let mut overrides = Self::default();
+
+        if let Some(path) = env_file {
+            let content = worktree
+                .read_text_file(path)
+                .map_err(|e| format!("unable to read {path}: {e}"))?;
+            let vars = parse_env_file(&content).map_err(|e| format!("{path}:{e}"))?;
+            for (key, value) in vars {
+                overrides.set(key, Some(value));
+            }
+        }
+
+        if let Some(env) = env {
+            let mut keys: Vec<&String> = env.keys().collect();
+            keys.sort();
+            for key in keys {
+                let value = Some(env[key].clone()).filter(|value| !value.is_empty());
+                overrides.set(key.clone(), value);
+            }
+        }
+
+        Ok(overrides)
+    }
+
+    fn set(&mut self, key: String, value: Option<String>) {
+        match self.vars.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.vars.push((key, value)),
+        }
+    }
+
+    /// Returns `env` with the overrides applied. Replaced variables keep their
+    /// position and new ones are appended.
+    pub fn apply(&self, mut env: Vec<(String, String)>) -> Vec<(String, String)> {
+        for (key, value) in &self.vars {
+            match value {
+                Some(value) => match env.iter_mut().find(|(existing, _)| existing == key) {
+                    Some((_, existing)) => existing.clone_from(value),
+                    None => env.push((key.clone(), value.clone())),
+                },
+                None => env.retain(|(existing, _)| existing != key),
+            }
+        }
+
+        env
+    }
+}
+
+/// Parses a dotenv file: `KEY=value` lines with an optional `export` prefix,
+/// `#` comments and single- or double-quoted values. Variables are not expanded.
+pub fn parse_env_file(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("{line_number}: expected KEY=value"))?;
+        let key = key.trim();
+        if !is_env_key(key) {
+            return Err(format!("{line_number}: invalid variable name '{key}'"));
+        }
+
+        let value = parse_env_value(value.trim())
+            .ok_or_else(|| format!("{line_number}: unterminated quote in {key}"))?;
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+fn is_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Unquotes a value, or returns `None` when a quote is not closed.
+fn parse_env_value(value: &str) -> Option<String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        let (quoted, _) = rest.split_once('\'')?;
+        return Some(quoted.to_string());
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut unquoted = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(unquoted),
+                '\\' => match chars.next()? {
+                    'n' => unquoted.push('\n'),
+                    't' => unquoted.push('\t'),
+                    other => unquoted.push(other),
+                },
+                c => unquoted.push(c),
+            }
+        }
+        return None;
+    }
+
+    let value = match value.find(" #") {
+        Some(comment) => &value[..comment],
+        None => value,
+    };
+    Some(value.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language_servers::language_server::FakeWorktree;
+
+    fn worktree_with_ruby(path: &str) -> FakeWorktree {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_which("ruby".to_string(), Some(path.to_string()));
+        worktree
+    }
+
+    #[test]
+    fn test_detect_version_manager_from_ruby_path() {
+        let cases = [
+            ("/home/me/.rbenv/shims/ruby", RubyVersionManager::Rbenv),
+            ("/home/me/.asdf/shims/ruby", RubyVersionManager::Asdf),
+            (
+                "/home/me/.local/share/mise/installs/ruby/3.3.0/bin/ruby",
+                RubyVersionManager::Mise,
+            ),
+            (
+                "/home/me/.rvm/rubies/ruby-3.3.0/bin/ruby",
+                RubyVersionManager::Rvm,
+            ),
+            (
+                "/home/me/.rubies/ruby-3.3.0/bin/ruby",
+                RubyVersionManager::Chruby,
+            ),
+            ("/opt/rubies/3.3.0/bin/ruby", RubyVersionManager::Chruby),
+            ("/usr/bin/ruby", RubyVersionManager::None),
+        ];
+
+        for (path, expected) in cases {
+            assert_eq!(
+                RubyVersionManager::detect(&worktree_with_ruby(path)),
+                Some(expected),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_shadowenv() {
+        let mut worktree = worktree_with_ruby("/usr/bin/ruby");
+        worktree.set_shell_env(vec![("__shadowenv_data".to_string(), "abc".to_string())]);
+
+        assert_eq!(
+            RubyVersionManager::detect(&worktree),
+            Some(RubyVersionManager::Shadowenv)
+        );
+    }
+
+    #[test]
+    fn test_detect_without_ruby() {
+        let worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        assert_eq!(RubyVersionManager::detect(&worktree), None);
+    }
+
+    #[test]
+    fn test_bundle_gemfile() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        assert_eq!(bundle_gemfile(&worktree), None);
+
+        worktree.add_file("gems.rb".to_string(), Ok(String::new()));
+        assert_eq!(
+            bundle_gemfile(&worktree).as_deref(),
+            Some("/path/to/project/gems.rb")
+        );
+
+        worktree.add_file("Gemfile".to_string(), Ok(String::new()));
+        assert_eq!(
+            bundle_gemfile(&worktree).as_deref(),
+            Some("/path/to/project/Gemfile")
+        );
+
+        worktree.set_shell_env(vec![(
+            "BUNDLE_GEMFILE".to_string(),
+            "gemfiles/rails.gemfile".to_string(),
+        )]);
+        assert_eq!(
+            bundle_gemfile(&worktree).as_deref(),
+            Some("/path/to/project/gemfiles/rails.gemfile")
+        );
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let content = r#"
+# Cache outside the project
+export RUBOCOP_CACHE_ROOT=/tmp/rubocop
+SRB_SKIP_GEM_RBIS = 1
+EMPTY=
+QUOTED="a # b\nc"
+LITERAL='$HOME \n'
+TRAILING=value # comment
+"#;
+
+        assert_eq!(
+            parse_env_file(content),
+            Ok(vars(&[
+                ("RUBOCOP_CACHE_ROOT", "/tmp/rubocop"),
+                ("SRB_SKIP_GEM_RBIS", "1"),
+                ("EMPTY", ""),
+                ("QUOTED", "a # b\nc"),
+                ("LITERAL", "$HOME \\n"),
+                ("TRAILING", "value"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_env_file_errors() {
+        assert_eq!(
+            parse_env_file("A=1\nNOT_AN_ASSIGNMENT"),
+            Err("2: expected KEY=value".to_string())
+        );
+        assert_eq!(
+            parse_env_file("1A=1"),
+            Err("1: invalid variable name '1A'".to_string())
+        );
+        assert_eq!(
+            parse_env_file("A=\"open"),
+            Err("1: unterminated quote in A".to_string())
+        );
+    }
+
+    #[test]
+    fn test_env_overrides_precedence() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(
+            ".env.rubocop".to_string(),
+            Ok("RUBOCOP_CACHE_ROOT=/tmp/file\nFROM_FILE=1\nPATH=/file/bin".to_string()),
+        );
+        let env = HashMap::from([
+            (
+                "RUBOCOP_CACHE_ROOT".to_string(),
+                "/tmp/settings".to_string(),
+            ),
+            ("HOME".to_string(), String::new()),
+        ]);
+
+        let overrides = EnvOverrides::load(&worktree, Some(".env.rubocop"), Some(&env))
+            .expect("overrides should load");
+
+        // The file overrides the shell, `env` overrides the file, and an
+        // empty value in `env` removes the variable.
+        assert_eq!(
+            overrides.apply(vars(&[
+                ("PATH", "/usr/bin"),
+                ("HOME", "/home/me"),
+                ("LANG", "C.UTF-8")
+            ])),
+            vars(&[
+                ("PATH", "/file/bin"),
+                ("LANG", "C.UTF-8"),
+                ("RUBOCOP_CACHE_ROOT", "/tmp/settings"),
+                ("FROM_FILE", "1"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_env_overrides_empty_file_value_is_kept() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(".env".to_string(), Ok("RUBYOPT=".to_string()));
+
+        let overrides =
+            EnvOverrides::load(&worktree, Some(".env"), None).expect("overrides should load");
+
+        assert_eq!(
+            overrides.apply(vars(&[("RUBYOPT", "-W0")])),
+            vars(&[("RUBYOPT", "")])
+        );
+    }
+
+    #[test]
+    fn test_env_overrides_missing_file() {
+        let worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        assert_eq!(
+            EnvOverrides::load(&worktree, Some(".env"), None),
+            Err("unable to read .env: File not found in mock: .env".to_string())
+        );
+        assert_eq!(
+            EnvOverrides::load(&worktree, None, None),
+            Ok(EnvOverrides::default())
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use super::language_server::LanguageServerBinary;
+use super::language_server::{LanguageServerBinary, ZedHost};
+use super::settings::NoSettings;
 use super::LanguageServer;
 use zed_extension_api::{self as zed};
 
@@ -8,11 +9,13 @@ impl LanguageServer for FuzzyRubyServer {
     const SERVER_ID: &str = "fuzzy-ruby-server";
     const EXECUTABLE_NAME: &str = "fuzzy";
     const GEM_NAME: &str = "fuzzy-ruby-server--not-a-gem";
+    type Settings = NoSettings;
 
     fn language_server_binary(
         &self,
         language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
+        _host: &ZedHost,
     ) -> zed::Result<LanguageServerBinary> {
         self.resolve_binary(language_server_id.as_ref(), worktree)
     }
@@ -99,6 +102,7 @@ mod tests {
             Ok(Some(LspBinarySettings {
                 path: Some(real_path.clone()),
                 arguments: None,
+                env: None,
             })),
         );
         let result = server.resolve_binary(FuzzyRubyServer::SERVER_ID, &mock_worktree);
@@ -115,6 +119,7 @@ mod tests {
             Ok(Some(LspBinarySettings {
                 path: Some("/nonexistent/fuzzy".to_string()),
                 arguments: None,
+                env: None,
             })),
         );
         let result = server.resolve_binary(FuzzyRubyServer::SERVER_ID, &mock_worktree);
@@ -1,9 +1,12 @@
-use std::{env, fs};
-
-use super::{language_server::WorktreeLike, LanguageServer};
+use super::{
+    language_server::{ExtensionHost, LanguageServerBinary, WorktreeLike, ZedHost},
+    settings::HerbSettings,
+    LanguageServer,
+};
 use zed_extension_api::{self as zed};
 
 const SERVER_PATH: &str = "node_modules/@herb-tools/language-server/bin/herb-language-server";
+const PROJECT_SERVER_PATH: &str = "node_modules/.bin/herb-language-server";
 const PACKAGE_NAME: &str = "@herb-tools/language-server";
 
 pub struct Herb {
@@ -14,6 +17,8 @@ impl LanguageServer for Herb {
     const SERVER_ID: &str = "herb";
     const EXECUTABLE_NAME: &str = "herb";
     const GEM_NAME: &str = "herb";
+    const CONFIGURATION_SECTION: Option<&str> = Some("languageServerHerb");
+    type Settings = HerbSettings;
 
     fn get_executable_args<T: WorktreeLike>(&self, _worktree: &T) -> Vec<String> {
         vec!["--stdio".to_string()]
@@ -32,60 +37,145 @@ impl Herb {
         language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
     ) -> zed::Result<zed::Command> {
-        let server_path = self.server_script_path(language_server_id, worktree)?;
+        let host = ZedHost::new(language_server_id);
+        let binary = self.resolve_binary(language_server_id.as_ref(), worktree, &host)?;
+        let env = Self::server_env(
+            language_server_id.as_ref(),
+            worktree,
+            &host,
+            binary.env.unwrap_or_default(),
+        )?;
+
+        self.finish_installation(language_server_id.as_ref(), worktree, &host);
 
         Ok(zed::Command {
-            command: zed::node_binary_path()?,
-            args: vec![
-                env::current_dir()
-                    .unwrap()
-                    .join(&server_path)
-                    .to_string_lossy()
-                    .into_owned(),
-                "--stdio".to_string(),
-            ],
-            env: worktree.shell_env(),
+            command: binary.path,
+            args: binary.args.unwrap_or(self.get_executable_args(worktree)),
+            env,
         })
     }
 
-    fn server_exists(&self) -> bool {
-        fs::metadata(SERVER_PATH).is_ok_and(|stat| stat.is_file())
+    /// Resolves the server in order: the configured `binary.path`, the
+    /// project's `node_modules` and finally the npm package managed by the
+    /// extension. The `herb` gem only ships a CLI, not the language server.
+    fn resolve_binary<T: WorktreeLike, H: ExtensionHost>(
+        &mut self,
+        server_id: &str,
+        worktree: &T,
+        host: &H,
+    ) -> zed::Result<LanguageServerBinary> {
+        if let Some(binary_settings) = worktree.lsp_binary_settings(server_id)? {
+            if let Some(path) = binary_settings.path {
+                return Ok(LanguageServerBinary {
+                    path,
+                    args: binary_settings.arguments,
+                    env: Some(worktree.shell_env()),
+                });
+            }
+        }
+
+        if worktree.read_text_file(PROJECT_SERVER_PATH).is_ok() {
+            return self.node_binary(
+                host,
+                format!("{}/{PROJECT_SERVER_PATH}", worktree.root_path()),
+                worktree,
+            );
+        }
+
+        let settings = Self::settings(server_id, worktree)?;
+        let server_path =
+            self.server_script_path(host, settings.server.package_version.as_deref())?;
+
+        self.node_binary(
+            host,
+            host.current_dir()?
+                .join(server_path)
+                .to_string_lossy()
+                .into_owned(),
+            worktree,
+        )
+    }
+
+    fn node_binary<T: WorktreeLike, H: ExtensionHost>(
+        &self,
+        host: &H,
+        script_path: String,
+        worktree: &T,
+    ) -> zed::Result<LanguageServerBinary> {
+        Ok(LanguageServerBinary {
+            path: host.node_binary_path()?,
+            args: Some(
+                std::iter::once(script_path)
+                    .chain(self.get_executable_args(worktree))
+                    .collect(),
+            ),
+            env: Some(worktree.shell_env()),
+        })
     }
 
-    fn server_script_path(
+    fn server_exists<H: ExtensionHost>(&self, host: &H) -> zed::Result<bool> {
+        Ok(host.current_dir()?.join(SERVER_PATH).is_file())
+    }
+
+    /// Installs the npm package unless a suitable version is already present.
+    ///
+    /// A pinned version is suitable when it matches the installed one, so the
+    /// registry is never queried, and failing to install it is an error.
+    /// Without a pin the latest version is looked up once per session, and an
+    /// existing install is kept if that lookup or the update fails.
+    fn server_script_path<H: ExtensionHost>(
         &mut self,
-        language_server_id: &zed::LanguageServerId,
-        _worktree: &zed::Worktree,
+        host: &H,
+        pinned_version: Option<&str>,
     ) -> zed::Result<String> {
-        let server_exists = self.server_exists();
-        if self.did_find_server && server_exists {
+        let server_exists = self.server_exists(host)?;
+        if pinned_version.is_none() && self.did_find_server && server_exists {
             return Ok(SERVER_PATH.to_string());
         }
 
-        zed::set_language_server_installation_status(
-            language_server_id,
-            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
-        );
-        let version = zed::npm_package_latest_version(PACKAGE_NAME)?;
-
-        if !server_exists
-            || zed::npm_package_installed_version(PACKAGE_NAME)?.as_ref() != Some(&version)
-        {
-            zed::set_language_server_installation_status(
-                language_server_id,
-                &zed::LanguageServerInstallationStatus::Downloading,
-            );
-            let result = zed::npm_install_package(PACKAGE_NAME, &version);
+        let installed_version = if server_exists {
+            host.npm_package_installed_version(PACKAGE_NAME)?
+        } else {
+            None
+        };
+
+        let version = match pinned_version {
+            Some(version) => version.to_string(),
+            None => {
+                host.set_installation_status(
+                    &zed::LanguageServerInstallationStatus::CheckingForUpdate,
+                );
+                match host.npm_package_latest_version(PACKAGE_NAME) {
+                    Ok(version) => version,
+                    Err(_) if server_exists => {
+                        self.did_find_server = true;
+                        return Ok(SERVER_PATH.to_string());
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        };
+
+        if !server_exists || installed_version.as_deref() != Some(version.as_str()) {
+            host.set_installation_status(&zed::LanguageServerInstallationStatus::Downloading);
+            let result = host.npm_install_package(PACKAGE_NAME, &version);
             match result {
                 Ok(()) => {
-                    if !self.server_exists() {
+                    if !self.server_exists(host)? {
                         Err(format!(
                                     "installed package '{PACKAGE_NAME}' did not contain expected path '{SERVER_PATH}'",
                                 ))?;
                     }
                 }
+                // A pinned version is what the user asked for, so running
+                // another one would hide the failure.
+                Err(error) if pinned_version.is_some() => {
+                    return Err(format!(
+                        "Failed to install {PACKAGE_NAME}@{version}, set by lsp.herb.settings.package_version: {error}"
+                    ));
+                }
                 Err(error) => {
-                    if !self.server_exists() {
+                    if !self.server_exists(host)? {
                         Err(error)?;
                     }
                 }
@@ -100,6 +190,55 @@ impl Herb {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::language_servers::language_server::{FakeHost, FakeWorktree, LspBinarySettings};
+    use std::path::{Path, PathBuf};
+
+    /// An extension directory under the system temporary directory, removed
+    /// when the test ends.
+    struct ExtensionDir(PathBuf);
+
+    impl ExtensionDir {
+        /// Creates the directory, optionally containing an npm install.
+        fn new(name: &str, with_server: bool) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("zed-ruby-herb-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).expect("failed to create extension dir");
+            let dir = Self(dir);
+            if with_server {
+                dir.write_server();
+            }
+            dir
+        }
+
+        fn write_server(&self) {
+            let server = self.0.join(SERVER_PATH);
+            std::fs::create_dir_all(server.parent().unwrap()).expect("failed to create package");
+            std::fs::write(server, "").expect("failed to write server script");
+        }
+    }
+
+    impl std::ops::Deref for ExtensionDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for ExtensionDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn npm_host(name: &str, installed_version: Option<&str>) -> (FakeHost, ExtensionDir) {
+        let dir = ExtensionDir::new(name, installed_version.is_some());
+        let mut host = FakeHost::command_free();
+        host.set_current_dir(dir.to_path_buf());
+        host.set_npm_installed_version(installed_version.map(str::to_string));
+        (host, dir)
+    }
 
     #[test]
     fn test_new_extension_initial_state() {
@@ -109,4 +248,230 @@ mod tests {
             "A new extension instance should have did_find_server as false by default."
         );
     }
+
+    #[test]
+    fn test_configured_binary_path() {
+        let mut herb = Herb::new();
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_lsp_binary_setting(
+            Herb::SERVER_ID.to_string(),
+            Ok(Some(LspBinarySettings {
+                path: Some("/custom/herb-language-server".to_string()),
+                arguments: Some(vec!["--stdio".to_string()]),
+                env: None,
+            })),
+        );
+        worktree.add_file(PROJECT_SERVER_PATH.to_string(), Ok(String::new()));
+
+        let binary = herb
+            .resolve_binary(Herb::SERVER_ID, &worktree, &FakeHost::command_free())
+            .expect("configured path should be used");
+
+        assert_eq!(binary.path, "/custom/herb-language-server");
+        assert_eq!(binary.args, Some(vec!["--stdio".to_string()]));
+    }
+
+    #[test]
+    fn test_project_node_modules() {
+        let mut herb = Herb::new();
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(PROJECT_SERVER_PATH.to_string(), Ok(String::new()));
+        let host = FakeHost::command_free();
+
+        let binary = herb
+            .resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect("project install should be used");
+
+        assert_eq!(binary.path, "/node/bin/node");
+        assert_eq!(
+            binary.args,
+            Some(vec![
+                "/path/to/project/node_modules/.bin/herb-language-server".to_string(),
+                "--stdio".to_string()
+            ])
+        );
+        assert!(host.npm_calls().is_empty());
+    }
+
+    #[test]
+    fn test_bundled_gem_is_not_used() {
+        let mut herb = Herb::new();
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_which("bundle".to_string(), Some("/bin/bundle".to_string()));
+        worktree.add_file(
+            "Gemfile.lock".to_string(),
+            Ok("DEPENDENCIES\n  herb\n".to_string()),
+        );
+        let (mut host, dir) = npm_host("bundled", Some("0.7.5"));
+        host.set_npm_latest_version(Ok("0.7.5".to_string()));
+
+        let binary = herb
+            .resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect("npm install should be used");
+
+        assert_eq!(binary.path, "/node/bin/node");
+        assert_eq!(
+            binary.args,
+            Some(vec![
+                dir.join(SERVER_PATH).to_string_lossy().into_owned(),
+                "--stdio".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_pinned_version_already_installed_skips_registry() {
+        let mut herb = Herb::new();
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_server_settings(
+            Herb::SERVER_ID.to_string(),
+            serde_json::json!({ "package_version": "0.7.5" }),
+        );
+        let (host, dir) = npm_host("pinned-installed", Some("0.7.5"));
+
+        let binary = herb
+            .resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect("pinned install should be used");
+
+        assert_eq!(binary.path, "/node/bin/node");
+        assert_eq!(
+            binary.args,
+            Some(vec![
+                dir.join(SERVER_PATH).to_string_lossy().into_owned(),
+                "--stdio".to_string()
+            ])
+        );
+        assert!(host.npm_calls().is_empty());
+        assert!(host.statuses().is_empty());
+    }
+
+    #[test]
+    fn test_pinned_version_mismatch_installs_pin() {
+        let mut herb = Herb::new();
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_server_settings(
+            Herb::SERVER_ID.to_string(),
+            serde_json::json!({ "package_version": "0.7.5" }),
+        );
+        let (host, _dir) = npm_host("pinned-mismatch", Some("0.8.0"));
+
+        herb.resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect("pinned version should be installed");
+
+        assert_eq!(
+            host.npm_calls(),
+            vec!["install @herb-tools/language-server@0.7.5"]
+        );
+    }
+
+    #[test]
+    fn test_pinned_version_install_failure_is_reported() {
+        let mut herb = Herb::new();
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_server_settings(
+            Herb::SERVER_ID.to_string(),
+            serde_json::json!({ "package_version": "0.7.5" }),
+        );
+        let (mut host, _dir) = npm_host("pinned-failure", Some("0.8.0"));
+        host.set_npm_install_result(Err("ETARGET no matching version".to_string()));
+
+        let error = herb
+            .resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect_err("the installed 0.8.0 should not replace the pin");
+
+        assert_eq!(
+            error,
+            "Failed to install @herb-tools/language-server@0.7.5, set by lsp.herb.settings.package_version: ETARGET no matching version"
+        );
+    }
+
+    #[test]
+    fn test_unpinned_installs_latest() {
+        let mut herb = Herb::new();
+        let worktree = FakeWorktree::new("/path/to/project".to_string());
+        let (mut host, dir) = npm_host("unpinned-missing", None);
+        host.set_npm_latest_version(Ok("0.8.0".to_string()));
+        let error = herb
+            .resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect_err("missing script after install should fail");
+        assert!(error.contains("did not contain expected path"), "{error}");
+
+        // The fake npm install does not write files, so provide the script.
+        dir.write_server();
+        herb.resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect("latest version should be installed");
+
+        assert_eq!(
+            host.npm_calls(),
+            vec![
+                "latest @herb-tools/language-server",
+                "install @herb-tools/language-server@0.8.0",
+                "latest @herb-tools/language-server",
+            ]
+        );
+        assert!(herb.did_find_server);
+    }
+
+    #[test]
+    fn test_unpinned_checks_registry_once_per_session() {
+        let mut herb = Herb::new();
+        let worktree = FakeWorktree::new("/path/to/project".to_string());
+        let (mut host, _dir) = npm_host("unpinned-session", Some("0.8.0"));
+        host.set_npm_latest_version(Ok("0.8.0".to_string()));
+
+        herb.resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect("installed version should be used");
+        herb.resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect("installed version should be used");
+
+        assert_eq!(host.npm_calls(), vec!["latest @herb-tools/language-server"]);
+    }
+
+    #[test]
+    fn test_unpinned_keeps_install_when_registry_unreachable() {
+        let mut herb = Herb::new();
+        let worktree = FakeWorktree::new("/path/to/project".to_string());
+        let (host, _dir) = npm_host("unpinned-offline", Some("0.7.0"));
+
+        herb.resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect("existing install should be used offline");
+
+        assert_eq!(host.npm_calls(), vec!["latest @herb-tools/language-server"]);
+    }
+
+    #[test]
+    fn test_unpinned_without_install_fails_when_registry_unreachable() {
+        let mut herb = Herb::new();
+        let worktree = FakeWorktree::new("/path/to/project".to_string());
+        let (host, _dir) = npm_host("unpinned-offline-missing", None);
+
+        let error = herb
+            .resolve_binary(Herb::SERVER_ID, &worktree, &host)
+            .expect_err("nothing to fall back to");
+
+        assert_eq!(error, "npm registry is not reachable");
+    }
+
+    #[test]
+    fn test_workspace_configuration() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_server_settings(
+            Herb::SERVER_ID.to_string(),
+            serde_json::json!({
+                "package_version": "0.7.5",
+                "linter": { "enabled": true },
+                "formatter": { "enabled": true, "indentWidth": 2 }
+            }),
+        );
+
+        assert_eq!(
+            Herb::workspace_configuration(Herb::SERVER_ID, &worktree),
+            Ok(Some(serde_json::json!({
+                "languageServerHerb": {
+                    "linter": { "enabled": true },
+                    "formatter": { "enabled": true, "indentWidth": 2 }
+                }
+            })))
+        );
+    }
 }
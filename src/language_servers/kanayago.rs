@@ -1,4 +1,11 @@
-use super::{language_server::WorktreeLike, LanguageServer};
+use zed_extension_api::{self as zed};
+
+use super::{
+    label::{symbol_label, CompletionHighlights},
+    language_server::WorktreeLike,
+    settings::NoSettings,
+    LanguageServer,
+};
 
 pub struct Kanayago {}
 
@@ -6,6 +13,7 @@ impl LanguageServer for Kanayago {
     const SERVER_ID: &str = "kanayago";
     const EXECUTABLE_NAME: &str = "kanayago";
     const GEM_NAME: &str = "kanayago";
+    type Settings = NoSettings;
 
     fn default_use_bundler() -> bool {
         false
@@ -16,10 +24,24 @@ impl LanguageServer for Kanayago {
     }
 }
 
+impl CompletionHighlights for Kanayago {}
+
 impl Kanayago {
     pub fn new() -> Self {
         Self {}
     }
+
+    pub fn label_for_completion(&self, completion: zed::lsp::Completion) -> Option<zed::CodeLabel> {
+        self.completion_label(
+            &completion.label,
+            &completion.kind?,
+            completion.detail.as_deref(),
+        )
+    }
+
+    pub fn label_for_symbol(&self, symbol: zed::lsp::Symbol) -> Option<zed::CodeLabel> {
+        symbol_label(&symbol)
+    }
 }
 
 #[cfg(test)]
@@ -48,4 +70,33 @@ mod tests {
     fn test_default_use_bundler() {
         assert!(!Kanayago::default_use_bundler());
     }
+
+    #[test]
+    fn test_completion_labels() {
+        use crate::language_servers::label::{completion, render_completions};
+        use zed_extension_api::lsp::CompletionKind;
+
+        let kanayago = Kanayago::new();
+        let completions = vec![
+            completion(
+                "fetch",
+                CompletionKind::Method,
+                Some("(key, default = nil)"),
+            ),
+            completion("save!", CompletionKind::Method, None),
+            completion("call", CompletionKind::Method, Some("Proc#call")),
+            completion("puts", CompletionKind::Function, Some("(*objects)")),
+            completion("User", CompletionKind::Class, None),
+            completion("VERSION", CompletionKind::Constant, Some("String")),
+            completion("nil", CompletionKind::Constant, None),
+            completion("if", CompletionKind::Keyword, None),
+            completion("@name", CompletionKind::Field, None),
+            completion("user", CompletionKind::Variable, None),
+            completion("each", CompletionKind::Snippet, None),
+        ];
+
+        insta::assert_snapshot!(render_completions(completions, |completion| {
+            kanayago.label_for_completion(completion)
+        }));
+    }
 }
@@ -0,0 +1,891 @@
+use zed_extension_api::{self as zed};
+
+const DEF: &str = "def ";
+
+/// Builds a method completion label from `def name(parameters) = ReturnType`,
+/// so Zed's Ruby grammar highlights the name, parameters, default values and
+/// return type. The label reads `name(parameters) -> ReturnType`.
+pub fn method_label(
+    name: &str,
+    parameters: Option<&str>,
+    return_type: Option<&str>,
+) -> zed::CodeLabel {
+    let signature = format!("{name}{}", parameters.unwrap_or_default());
+    let mut spans = vec![zed::CodeLabelSpan::code_range(
+        DEF.len()..DEF.len() + signature.len(),
+    )];
+
+    let code = match return_type {
+        Some(return_type) => {
+            let code = format!("{DEF}{signature} = {return_type}");
+            spans.push(zed::CodeLabelSpan::literal(" -> ", None));
+            spans.push(zed::CodeLabelSpan::code_range(
+                code.len() - return_type.len()..code.len(),
+            ));
+            code
+        }
+        None => format!("{DEF}{signature}; end"),
+    };
+
+    zed::CodeLabel {
+        code,
+        spans,
+        filter_range: (0..name.len()).into(),
+    }
+}
+
+/// Splits a leading parameter list, such as `(name, opts = {}) => String`,
+/// into `(name, opts = {})` and the rest, or returns `None` when `detail` does
+/// not start with a balanced parameter list.
+pub fn split_parameters(detail: &str) -> Option<(&str, &str)> {
+    if !detail.starts_with('(') {
+        return None;
+    }
+
+    let mut depth = 0;
+    for (index, c) in detail.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(detail.split_at(index + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Builds the label of a value completion, a variable or constant, whose
+/// server reports its type: the name in `highlight`, then the type as Ruby code.
+pub fn typed_label(name: &str, highlight: &str, type_name: &str) -> zed::CodeLabel {
+    zed::CodeLabel {
+        code: type_name.to_string(),
+        spans: vec![
+            zed::CodeLabelSpan::literal(name, Some(highlight.to_string())),
+            zed::CodeLabelSpan::literal(" ", None),
+            zed::CodeLabelSpan::code_range(0..type_name.len()),
+        ],
+        filter_range: (0..name.len()).into(),
+    }
+}
+
+/// Builds the label of a completion the server reports with only a name:
+/// the name in `highlight`, then the detail without highlighting.
+pub fn plain_label(name: &str, highlight: &str, detail: Option<&str>) -> zed::CodeLabel {
+    let mut spans = vec![zed::CodeLabelSpan::literal(
+        name,
+        Some(highlight.to_string()),
+    )];
+    if let Some(detail) = detail {
+        spans.push(zed::CodeLabelSpan::literal(" ", None));
+        spans.push(zed::CodeLabelSpan::literal(detail, None));
+    }
+
+    zed::CodeLabel {
+        code: String::new(),
+        spans,
+        filter_range: (0..name.len()).into(),
+    }
+}
+
+/// How a server's completion kinds map to highlights. A server opts in with
+/// `impl CompletionHighlights for Server {}` and overrides the methods for the
+/// kinds it reports differently.
+pub trait CompletionHighlights {
+    /// Whether completions of `kind` are methods, labeled as signatures.
+    fn is_method(&self, kind: &zed::lsp::CompletionKind) -> bool {
+        matches!(
+            kind,
+            zed::lsp::CompletionKind::Method
+                | zed::lsp::CompletionKind::Function
+                | zed::lsp::CompletionKind::Constructor
+        )
+    }
+
+    /// Whether completions of `kind` name a value, whose detail is its type.
+    fn is_value(&self, kind: &zed::lsp::CompletionKind) -> bool {
+        matches!(
+            kind,
+            zed::lsp::CompletionKind::Constant
+                | zed::lsp::CompletionKind::Field
+                | zed::lsp::CompletionKind::Variable
+                | zed::lsp::CompletionKind::Property
+        )
+    }
+
+    /// The highlight of a completion's name, or `None` for kinds that keep
+    /// Zed's default label.
+    fn name_highlight(&self, kind: &zed::lsp::CompletionKind, name: &str) -> Option<&'static str> {
+        if self.is_method(kind) {
+            return Some("function.method");
+        }
+        default_name_highlight(kind, name)
+    }
+
+    /// Builds a label for completions whose `detail` format is not known:
+    /// methods get their parameter list highlighted when the detail is one,
+    /// and every other detail is shown as is.
+    fn completion_label(
+        &self,
+        name: &str,
+        kind: &zed::lsp::CompletionKind,
+        detail: Option<&str>,
+    ) -> Option<zed::CodeLabel> {
+        if !self.is_method(kind) {
+            return Some(plain_label(name, self.name_highlight(kind, name)?, detail));
+        }
+
+        let parameters = detail.filter(|detail| {
+            split_parameters(detail).is_some_and(|(_, rest)| rest.trim().is_empty())
+        });
+        let mut label = method_label(name, parameters, None);
+        if let Some(detail) = detail.filter(|_| parameters.is_none()) {
+            label.spans.push(zed::CodeLabelSpan::literal(" ", None));
+            label.spans.push(zed::CodeLabelSpan::literal(detail, None));
+        }
+
+        Some(label)
+    }
+}
+
+/// The shared highlight of a completion's name, for overrides of
+/// [`CompletionHighlights::name_highlight`] to fall back to.
+pub fn default_name_highlight(kind: &zed::lsp::CompletionKind, name: &str) -> Option<&'static str> {
+    let highlight = match kind {
+        zed::lsp::CompletionKind::Class | zed::lsp::CompletionKind::Module => "type",
+        zed::lsp::CompletionKind::Constant if name == "nil" => "constant.builtin",
+        zed::lsp::CompletionKind::Constant if name.starts_with("__") && name.ends_with("__") => {
+            "constant.builtin"
+        }
+        zed::lsp::CompletionKind::Constant => "constant",
+        zed::lsp::CompletionKind::Method
+        | zed::lsp::CompletionKind::Function
+        | zed::lsp::CompletionKind::Constructor => "function.method",
+        zed::lsp::CompletionKind::Keyword => "keyword",
+        zed::lsp::CompletionKind::Field if name.starts_with('@') => "variable.special",
+        zed::lsp::CompletionKind::Field if name == "self" || name == "super" => "variable.special",
+        zed::lsp::CompletionKind::Variable => "variable",
+        zed::lsp::CompletionKind::Property => "property",
+        _ => return None,
+    };
+
+    Some(highlight)
+}
+
+/// Builds the label of a method from its RBS method type, such as
+/// `(::Integer index) -> ::String`, which reads `name(::Integer index) -> ::String`.
+/// Type parameters and blocks are set apart, as in `map [U] () { (Elem) -> U } -> U`.
+pub fn rbs_method_label(name: &str, method_type: &str) -> zed::CodeLabel {
+    let mut spans = vec![zed::CodeLabelSpan::literal(
+        name,
+        Some("function.method".to_string()),
+    )];
+    if method_type.starts_with(['[', '{']) {
+        spans.push(zed::CodeLabelSpan::literal(" ", None));
+    }
+    spans.extend(rbs_spans(method_type));
+
+    zed::CodeLabel {
+        code: String::new(),
+        spans,
+        filter_range: (0..name.len()).into(),
+    }
+}
+
+const RBS_BUILTIN_TYPES: &[&str] = &[
+    "bool", "boolish", "bot", "class", "instance", "top", "untyped", "void",
+];
+
+/// Highlights an RBS type or method type with the scopes of
+/// `languages/rbs/highlights.scm`.
+///
+/// Zed parses label code with the grammar of the buffer, which is Ruby for
+/// these servers, so RBS is split into highlighted literals instead.
+pub fn rbs_spans(rbs: &str) -> Vec<zed::CodeLabelSpan> {
+    let type_variables = rbs_type_variables(rbs);
+    let mut spans: Vec<zed::CodeLabelSpan> = Vec::new();
+    let mut push = |text: &str, highlight: Option<&str>| {
+        if let (None, Some(zed::CodeLabelSpan::Literal(last))) = (highlight, spans.last_mut()) {
+            if last.highlight_name.is_none() {
+                last.text.push_str(text);
+                return;
+            }
+        }
+        spans.push(zed::CodeLabelSpan::literal(
+            text,
+            highlight.map(str::to_string),
+        ));
+    };
+
+    let mut rest = rbs;
+    let mut previous = "";
+    let mut after_type = false;
+    let mut depth = 0;
+    while let Some(c) = rest.chars().next() {
+        let next = rest[c.len_utf8()..].chars().next();
+        let name_len = rbs_name_len(rest);
+
+        let (len, highlight) = if c.is_whitespace() {
+            let len = rest.len() - rest.trim_start().len();
+            push(&rest[..len], None);
+            rest = &rest[len..];
+            continue;
+        } else if rest.starts_with("->") || rest.starts_with("**") {
+            (2, Some("operator"))
+        } else if c == '"' || c == '\'' {
+            let len = rest[1..].find(c).map_or(rest.len(), |end| end + 2);
+            (len, Some("string"))
+        } else if c.is_ascii_digit() || (c == '-' && next.is_some_and(|c| c.is_ascii_digit())) {
+            let len = rest[1..]
+                .find(|c: char| !c.is_ascii_digit() && c != '_')
+                .map_or(rest.len(), |end| end + 1);
+            (len, Some("number"))
+        } else if c == ':' && next.is_some_and(|c| c.is_alphabetic() || c == '_') {
+            let len = 1 + rbs_name_len(&rest[1..]);
+            (with_suffix(rest, len), Some("string.special.symbol"))
+        } else if name_len > 0 {
+            let name = &rest[..name_len];
+            if previous == "#" || previous == "." {
+                (with_suffix(rest, name_len), Some("function.method"))
+            } else if let Some(separator) = name.rfind("::") {
+                let namespace = &name[..separator + 2];
+                let highlight = namespace
+                    .chars()
+                    .any(char::is_alphanumeric)
+                    .then_some("module");
+                push(namespace, highlight);
+                push(&name[separator + 2..], Some("type"));
+                rest = &rest[name_len..];
+                previous = "type";
+                after_type = true;
+                continue;
+            } else if type_variables.contains(&name) {
+                (name_len, Some("constant"))
+            } else if RBS_BUILTIN_TYPES.contains(&name) {
+                (name_len, Some("type.builtin"))
+            } else if name == "nil" {
+                (name_len, Some("constant.builtin"))
+            } else if name == "true" || name == "false" {
+                (name_len, Some("boolean"))
+            } else if name == "self" {
+                (name_len, Some("variable.builtin"))
+            } else if name.starts_with(|c: char| c.is_uppercase() || c == '_') {
+                (name_len, Some("type"))
+            } else if (rest[name_len..].starts_with(':') && !rest[name_len..].starts_with("::"))
+                || (after_type && depth > 0)
+            {
+                // A keyword, as in `key: T`, or a positional name, as in `(T name)`.
+                (name_len, Some("variable.parameter"))
+            } else {
+                (name_len, Some("type"))
+            }
+        } else {
+            let highlight = match c {
+                '(' | ')' | '[' | ']' | '{' | '}' => Some("punctuation.bracket"),
+                ',' | '.' | '#' => Some("punctuation.delimiter"),
+                '|' | '^' | '?' | '*' | '&' | '<' | '=' => Some("operator"),
+                _ => None,
+            };
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            (c.len_utf8(), highlight)
+        };
+
+        let token = &rest[..len];
+        push(token, highlight);
+        after_type = match highlight {
+            Some(
+                "type"
+                | "type.builtin"
+                | "constant"
+                | "constant.builtin"
+                | "boolean"
+                | "variable.builtin"
+                | "string"
+                | "number"
+                | "string.special.symbol",
+            ) => true,
+            Some("operator") if token == "?" => after_type,
+            _ => token == "]",
+        };
+        previous = token;
+        rest = &rest[len..];
+    }
+
+    spans
+}
+
+/// The length of the constant path or identifier `s` starts with, such as
+/// `::Array` or `Foo::bar`, or 0.
+fn rbs_name_len(s: &str) -> usize {
+    let mut len = 0;
+    loop {
+        let prefix = if s[len..].starts_with("::") { 2 } else { 0 };
+        if (len > 0 && prefix == 0)
+            || !s[len + prefix..].starts_with(|c: char| c.is_alphabetic() || c == '_')
+        {
+            return len;
+        }
+        let segment = s[len + prefix..]
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(s.len() - len - prefix);
+        len += prefix + segment;
+    }
+}
+
+/// Extends a method or symbol name by its `?`, `!` or `=` suffix.
+fn with_suffix(s: &str, len: usize) -> usize {
+    match s[len..].chars().next() {
+        Some('?' | '!' | '=') if !s[len..].starts_with("=>") => len + 1,
+        _ => len,
+    }
+}
+
+/// The type parameters a method type declares, such as `T` in `[T] (T) -> T`.
+fn rbs_type_variables(rbs: &str) -> Vec<&str> {
+    let Some(parameters) = rbs
+        .trim_start()
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(parameters, _)| parameters)
+    else {
+        return Vec::new();
+    };
+
+    parameters
+        .split(',')
+        .filter_map(|parameter| {
+            parameter
+                .split_whitespace()
+                .find(|word| !matches!(*word, "in" | "out" | "unchecked"))
+        })
+        .collect()
+}
+
+/// Builds the label of a document or workspace symbol from its name.
+///
+/// Solargraph qualifies names, as in `Foo#bar`, `Foo.bar` and `Foo::VERSION`,
+/// while Ruby LSP reports them as written, as in `bar`, `self.bar` and
+/// `attr_reader :name`, so both forms are understood.
+pub fn symbol_label(symbol: &zed::lsp::Symbol) -> Option<zed::CodeLabel> {
+    let name = symbol.name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    match symbol.kind {
+        zed::lsp::SymbolKind::Method
+        | zed::lsp::SymbolKind::Function
+        | zed::lsp::SymbolKind::Constructor => Some(method_symbol_label(name)),
+        zed::lsp::SymbolKind::Class => Some(definition_label("class ", name)),
+        zed::lsp::SymbolKind::Module
+        | zed::lsp::SymbolKind::Namespace
+        | zed::lsp::SymbolKind::Package => Some(definition_label("module ", name)),
+        zed::lsp::SymbolKind::Constant => Some(constant_symbol_label(name)),
+        zed::lsp::SymbolKind::Field
+        | zed::lsp::SymbolKind::Property
+        | zed::lsp::SymbolKind::Variable => Some(attribute_symbol_label(&symbol.kind, name)),
+        _ => None,
+    }
+}
+
+/// Builds a label from `keyword name; end`, so a namespace such as `A::B::C`
+/// is highlighted by the grammar.
+fn definition_label(keyword: &str, name: &str) -> zed::CodeLabel {
+    zed::CodeLabel {
+        code: format!("{keyword}{name}; end"),
+        spans: vec![zed::CodeLabelSpan::code_range(
+            keyword.len()..keyword.len() + name.len(),
+        )],
+        filter_range: (0..name.len()).into(),
+    }
+}
+
+/// Builds a method symbol label from `def name; end`, or `def self.name; end`
+/// for singleton methods, keeping the owner the server named.
+fn method_symbol_label(name: &str) -> zed::CodeLabel {
+    let (owner, method, singleton) = if let Some((owner, method)) = name.rsplit_once('#') {
+        (Some(owner), method, false)
+    } else if let Some(method) = name.strip_prefix("self.") {
+        (None, method, true)
+    } else if let Some((owner, method)) = name
+        .rsplit_once('.')
+        .or_else(|| name.rsplit_once("::"))
+        .filter(|(owner, method)| {
+            !owner.is_empty() && method.starts_with(|c: char| !c.is_uppercase())
+        })
+    {
+        (Some(owner), method, true)
+    } else {
+        (None, name, false)
+    };
+
+    let definition = if singleton { "def self." } else { "def " };
+    let code = format!("{definition}{method}; end");
+    let method_range = definition.len()..definition.len() + method.len();
+    let text_len = owner.map_or(method_range.end - DEF.len(), |owner| {
+        owner.len() + 1 + method.len()
+    });
+
+    let mut spans = Vec::new();
+    match owner {
+        Some(owner) => {
+            spans.extend(constant_path_spans(owner));
+            if singleton {
+                // The `.` before the name, as in `self.name`.
+                spans.push(zed::CodeLabelSpan::code_range(
+                    method_range.start - 1..method_range.end,
+                ));
+            } else {
+                spans.push(zed::CodeLabelSpan::literal("#", None));
+                spans.push(zed::CodeLabelSpan::code_range(method_range));
+            }
+        }
+        None => spans.push(zed::CodeLabelSpan::code_range(DEF.len()..method_range.end)),
+    }
+
+    zed::CodeLabel {
+        code,
+        spans,
+        filter_range: (0..text_len).into(),
+    }
+}
+
+/// Highlights a namespace such as `A::B` given as text rather than code.
+fn constant_path_spans(path: &str) -> Vec<zed::CodeLabelSpan> {
+    let mut spans = Vec::new();
+    for (index, segment) in path.split("::").enumerate() {
+        if index > 0 {
+            spans.push(zed::CodeLabelSpan::literal("::", None));
+        }
+        spans.push(zed::CodeLabelSpan::literal(
+            segment,
+            Some("type".to_string()),
+        ));
+    }
+    spans
+}
+
+/// Builds a constant symbol label from its name, or from `NAME = value` when
+/// the server includes the value.
+fn constant_symbol_label(name: &str) -> zed::CodeLabel {
+    let constant_len = name
+        .find('=')
+        .map_or(name.len(), |index| name[..index].trim_end().len());
+
+    zed::CodeLabel {
+        code: name.to_string(),
+        spans: vec![zed::CodeLabelSpan::code_range(0..name.len())],
+        filter_range: (0..constant_len).into(),
+    }
+}
+
+/// Builds the label of an instance, class or global variable from its code,
+/// of an `attr_*` declaration such as `attr_reader :name` filtered by the
+/// attribute's name, and of any other field or property from its name.
+fn attribute_symbol_label(kind: &zed::lsp::SymbolKind, name: &str) -> zed::CodeLabel {
+    if name.starts_with(['@', '$']) {
+        return zed::CodeLabel {
+            code: name.to_string(),
+            spans: vec![zed::CodeLabelSpan::code_range(0..name.len())],
+            filter_range: (0..name.len()).into(),
+        };
+    }
+
+    if name.starts_with("attr_") {
+        if let Some((_, attribute)) = name.split_once(char::is_whitespace) {
+            let attribute = attribute.trim_start().trim_start_matches(':');
+            let start = name.len() - attribute.len();
+            return zed::CodeLabel {
+                code: name.to_string(),
+                spans: vec![zed::CodeLabelSpan::code_range(0..name.len())],
+                filter_range: (start..name.len()).into(),
+            };
+        }
+    }
+
+    let highlight = match kind {
+        zed::lsp::SymbolKind::Variable => "variable",
+        _ => "property",
+    };
+    plain_label(name, highlight, None)
+}
+
+/// Renders a label the way Zed displays it, with the highlights of the Ruby
+/// grammar for each code range, for snapshot tests.
+#[cfg(test)]
+pub fn render_label(label: &zed::CodeLabel) -> String {
+    use tree_sitter::{Parser, Query, QueryCursor, StreamingIterator};
+
+    let language: tree_sitter::Language = tree_sitter_ruby::LANGUAGE.into();
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language)
+        .expect("Error loading ruby parser");
+    let tree = parser
+        .parse(&label.code, None)
+        .expect("Failed to parse label code");
+    static QUERY: std::sync::OnceLock<Query> = std::sync::OnceLock::new();
+    let query = QUERY.get_or_init(|| {
+        let query_source = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("languages/ruby/highlights.scm"),
+        )
+        .expect("Failed to read highlights.scm");
+        Query::new(&language, &query_source).expect("Failed to create query")
+    });
+
+    // Every capture of a node, in the order of the query patterns.
+    let mut captures = Vec::new();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(query, tree.root_node(), label.code.as_bytes());
+    while let Some(query_match) = matches.next() {
+        for capture in query_match.captures {
+            let name = query.capture_names()[capture.index as usize];
+            let range = capture.node.byte_range();
+            // Nodes the parser inserted to recover from an error are empty.
+            if !name.starts_with('_') && !range.is_empty() {
+                captures.push((range.start, range.end, query_match.pattern_index, name));
+            }
+        }
+    }
+    captures.sort_by_key(|&(start, end, pattern, _)| (start, std::cmp::Reverse(end), pattern));
+    captures.dedup();
+
+    let mut nodes: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+    for (start, end, _, name) in captures {
+        match nodes.last_mut() {
+            Some((range, names)) if *range == (start..end) => {
+                names.push_str(&format!(" @{name}"));
+            }
+            _ => nodes.push((start..end, format!("@{name}"))),
+        }
+    }
+
+    let mut text = String::new();
+    let mut highlights = Vec::new();
+    for span in &label.spans {
+        match span {
+            zed::CodeLabelSpan::CodeRange(range) => {
+                let range = range.start as usize..range.end as usize;
+                text.push_str(&label.code[range.clone()]);
+                for (node, names) in &nodes {
+                    if range.start <= node.start && node.end <= range.end {
+                        highlights.push(format!("{:?} {names}", &label.code[node.clone()]));
+                    }
+                }
+            }
+            zed::CodeLabelSpan::Literal(literal) => {
+                text.push_str(&literal.text);
+                if let Some(name) = &literal.highlight_name {
+                    highlights.push(format!("{:?} @{name}", literal.text));
+                }
+            }
+        }
+    }
+
+    let filter = &text[label.filter_range.start as usize..label.filter_range.end as usize];
+    let mut rendered = format!("label: {text}\nfilter: {filter}\ncode: {}\n", label.code);
+    for highlight in highlights {
+        rendered.push_str(&format!("  {highlight}\n"));
+    }
+    rendered
+}
+
+/// Renders the label of each completion under a heading describing what the
+/// server sent, for snapshot tests of a server's `label_for_completion`.
+#[cfg(test)]
+pub fn render_completions(
+    completions: Vec<zed::lsp::Completion>,
+    label_for_completion: impl Fn(zed::lsp::Completion) -> Option<zed::CodeLabel>,
+) -> String {
+    let mut rendered = Vec::new();
+    for completion in completions {
+        let kind = completion
+            .kind
+            .as_ref()
+            .map_or_else(|| "None".to_string(), |kind| format!("{kind:?}"));
+        let mut heading = format!("## {kind} {:?}", completion.label);
+        if let Some(detail) = &completion.detail {
+            heading.push_str(&format!(" detail={detail:?}"));
+        }
+        let label = label_for_completion(completion)
+            .map_or_else(|| "no label\n".to_string(), |label| render_label(&label));
+        rendered.push(format!("{heading}\n{label}"));
+    }
+    rendered.join("\n")
+}
+
+/// A completion as a server sends it, for tests.
+#[cfg(test)]
+pub fn completion(
+    label: &str,
+    kind: zed::lsp::CompletionKind,
+    detail: Option<&str>,
+) -> zed::lsp::Completion {
+    zed::lsp::Completion {
+        label: label.to_string(),
+        label_details: None,
+        detail: detail.map(str::to_string),
+        kind: Some(kind),
+        insert_text_format: None,
+    }
+}
+
+/// Every completion kind, with the special names servers report.
+#[cfg(test)]
+pub fn completion_kind_matrix() -> Vec<(zed::lsp::CompletionKind, &'static str)> {
+    use zed_extension_api::lsp::CompletionKind;
+
+    vec![
+        (CompletionKind::Text, "text"),
+        (CompletionKind::Method, "save!"),
+        (CompletionKind::Function, "puts"),
+        (CompletionKind::Constructor, "new"),
+        (CompletionKind::Field, "name"),
+        (CompletionKind::Field, "@name"),
+        (CompletionKind::Field, "@@var"),
+        (CompletionKind::Field, "self"),
+        (CompletionKind::Field, "super"),
+        (CompletionKind::Variable, "user"),
+        (CompletionKind::Class, "User"),
+        (CompletionKind::Interface, "_Each"),
+        (CompletionKind::Module, "Comparable"),
+        (CompletionKind::Property, "email"),
+        (CompletionKind::Unit, "unit"),
+        (CompletionKind::Value, "value"),
+        (CompletionKind::Enum, "Enum"),
+        (CompletionKind::Keyword, "def"),
+        (CompletionKind::Keyword, ":sym"),
+        (CompletionKind::Snippet, "each"),
+        (CompletionKind::Color, "red"),
+        (CompletionKind::File, "user.rb"),
+        (CompletionKind::Reference, "run"),
+        (CompletionKind::Folder, "app"),
+        (CompletionKind::EnumMember, "MEMBER"),
+        (CompletionKind::Constant, "VERSION"),
+        (CompletionKind::Constant, "nil"),
+        (CompletionKind::Constant, "__FILE__"),
+        (CompletionKind::Struct, "Point"),
+        (CompletionKind::Event, "event"),
+        (CompletionKind::Operator, "=="),
+        (CompletionKind::TypeParameter, "T"),
+        (CompletionKind::Other(42), "other"),
+    ]
+}
+
+/// Renders each name's highlight over [`completion_kind_matrix`], for
+/// tests of a server's [`CompletionHighlights`].
+#[cfg(test)]
+pub fn render_highlights(highlights: &impl CompletionHighlights) -> String {
+    completion_kind_matrix()
+        .into_iter()
+        .map(|(kind, name)| {
+            let highlight = highlights
+                .name_highlight(&kind, name)
+                .map_or_else(|| "-".to_string(), |highlight| format!("@{highlight}"));
+            format!("{kind:?} {name:?} {highlight}\n")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_parameters() {
+        assert_eq!(
+            split_parameters("(name, opts = {}) => String"),
+            Some(("(name, opts = {})", " => String"))
+        );
+        assert_eq!(
+            split_parameters("(items = [(1)])"),
+            Some(("(items = [(1)])", ""))
+        );
+        assert_eq!(split_parameters("=> String"), None);
+        assert_eq!(split_parameters("(unclosed"), None);
+    }
+
+    #[test]
+    fn test_method_label() {
+        insta::assert_snapshot!(
+            render_label(&method_label("fetch", Some("(key, default = nil)"), Some("String"))),
+            @r#"
+        label: fetch(key, default = nil) -> String
+        filter: fetch
+        code: def fetch(key, default = nil) = String
+          "fetch" @variable @function.method.definition
+          "(" @punctuation.bracket
+          "key" @variable @variable.parameter
+          "," @punctuation.delimiter
+          "default" @variable @variable.parameter
+          "=" @operator
+          "nil" @constant.builtin
+          ")" @punctuation.bracket
+          "String" @type
+        "#
+        );
+    }
+
+    fn rbs_tokens(rbs: &str) -> Vec<(String, Option<String>)> {
+        rbs_spans(rbs)
+            .into_iter()
+            .map(|span| match span {
+                zed::CodeLabelSpan::Literal(literal) => (literal.text, literal.highlight_name),
+                zed::CodeLabelSpan::CodeRange(_) => panic!("RBS spans are literals"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rbs_spans() {
+        let tokens = rbs_tokens("[T] (::Foo::Bar name, ?key: T?) { -> void } -> :ok");
+        let highlighted: Vec<(&str, &str)> = tokens
+            .iter()
+            .filter_map(|(text, highlight)| Some((text.as_str(), highlight.as_deref()?)))
+            .collect();
+        assert_eq!(
+            highlighted,
+            [
+                ("[", "punctuation.bracket"),
+                ("T", "constant"),
+                ("]", "punctuation.bracket"),
+                ("(", "punctuation.bracket"),
+                ("::Foo::", "module"),
+                ("Bar", "type"),
+                ("name", "variable.parameter"),
+                (",", "punctuation.delimiter"),
+                ("?", "operator"),
+                ("key", "variable.parameter"),
+                ("T", "constant"),
+                ("?", "operator"),
+                (")", "punctuation.bracket"),
+                ("{", "punctuation.bracket"),
+                ("->", "operator"),
+                ("void", "type.builtin"),
+                ("}", "punctuation.bracket"),
+                ("->", "operator"),
+                (":ok", "string.special.symbol"),
+            ]
+        );
+        let text: String = tokens.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(text, "[T] (::Foo::Bar name, ?key: T?) { -> void } -> :ok");
+    }
+
+    #[test]
+    fn test_rbs_spans_use_rbs_grammar_scopes() {
+        let highlights = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("languages/rbs/highlights.scm"),
+        )
+        .expect("Failed to read highlights.scm");
+        let rbs = "(::Array[Integer] items, \"a\", 1) -> bool | nil | true | self | alias \
+                   | ::Kernel#puts | Foo.bar? | ^(untyped) -> void | *untyped";
+
+        for (text, highlight) in rbs_tokens(rbs) {
+            if let Some(highlight) = highlight {
+                assert!(
+                    highlights.contains(&format!("@{highlight}")),
+                    "{text:?} is highlighted as @{highlight}, which the RBS grammar does not use"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_symbol_labels() {
+        use zed_extension_api::lsp::{Symbol, SymbolKind};
+
+        let symbols = [
+            (SymbolKind::Class, "User"),
+            (SymbolKind::Class, "Admin::Users::Invite"),
+            (SymbolKind::Module, "Comparable"),
+            (SymbolKind::Namespace, "Api::V1"),
+            (SymbolKind::Method, "save!"),
+            (SymbolKind::Method, "User#save!"),
+            (SymbolKind::Method, "Admin::User#name="),
+            (SymbolKind::Method, "self.find"),
+            (SymbolKind::Method, "User.find"),
+            (SymbolKind::Method, "Admin::User::find_by"),
+            (SymbolKind::Constructor, "initialize"),
+            (SymbolKind::Constant, "VERSION"),
+            (SymbolKind::Constant, "Admin::ROLES"),
+            (SymbolKind::Constant, "TIMEOUT = 30"),
+            (SymbolKind::Field, "@name"),
+            (SymbolKind::Field, "@@count"),
+            (SymbolKind::Variable, "$stdout"),
+            (SymbolKind::Property, "attr_reader :name"),
+            (SymbolKind::Property, "attr_accessor :email"),
+            (SymbolKind::Property, "email"),
+            (SymbolKind::Variable, "user"),
+            (SymbolKind::File, "user.rb"),
+        ];
+
+        let rendered: Vec<String> = symbols
+            .into_iter()
+            .map(|(kind, name)| {
+                let heading = format!("## {kind:?} {name:?}");
+                let label = symbol_label(&Symbol {
+                    kind,
+                    name: name.to_string(),
+                })
+                .map_or_else(|| "no label\n".to_string(), |label| render_label(&label));
+                format!("{heading}\n{label}")
+            })
+            .collect();
+
+        insta::assert_snapshot!(rendered.join("\n"));
+    }
+
+    struct Shared;
+
+    impl CompletionHighlights for Shared {}
+
+    #[test]
+    fn test_shared_completion_highlights() {
+        insta::assert_snapshot!(
+            render_highlights(&Shared),
+            @r#"
+        CompletionKind::Text "text" -
+        CompletionKind::Method "save!" @function.method
+        CompletionKind::Function "puts" @function.method
+        CompletionKind::Constructor "new" @function.method
+        CompletionKind::Field "name" -
+        CompletionKind::Field "@name" @variable.special
+        CompletionKind::Field "@@var" @variable.special
+        CompletionKind::Field "self" @variable.special
+        CompletionKind::Field "super" @variable.special
+        CompletionKind::Variable "user" @variable
+        CompletionKind::Class "User" @type
+        CompletionKind::Interface "_Each" -
+        CompletionKind::Module "Comparable" @type
+        CompletionKind::Property "email" @property
+        CompletionKind::Unit "unit" -
+        CompletionKind::Value "value" -
+        CompletionKind::Enum "Enum" -
+        CompletionKind::Keyword "def" @keyword
+        CompletionKind::Keyword ":sym" @keyword
+        CompletionKind::Snippet "each" -
+        CompletionKind::Color "red" -
+        CompletionKind::File "user.rb" -
+        CompletionKind::Reference "run" -
+        CompletionKind::Folder "app" -
+        CompletionKind::EnumMember "MEMBER" -
+        CompletionKind::Constant "VERSION" @constant
+        CompletionKind::Constant "nil" @constant.builtin
+        CompletionKind::Constant "__FILE__" @constant.builtin
+        CompletionKind::Struct "Point" -
+        CompletionKind::Event "event" -
+        CompletionKind::Operator "==" -
+        CompletionKind::TypeParameter "T" -
+        CompletionKind::Other(42) "other" -
+        "#
+        );
+    }
+}
@@ -1,14 +1,22 @@
+use std::cell::OnceCell;
 #[cfg(test)]
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
+use super::environment::EnvOverrides;
+use super::project::{Activation, ProjectMarkers};
+use super::settings::{self, ExtensionSettings, Settings};
 #[cfg(feature = "command_api")]
+use crate::command_executor::RealCommandExecutor;
+#[cfg(test)]
+use crate::command_executor::ScriptedCommandExecutor;
 use crate::{
     bundler::Bundler,
-    command_executor::RealCommandExecutor,
-    gemset::{versioned_gem_home, Gemset},
+    command_executor::CommandExecutor,
+    gemset::{interpreter_gem_home, Gemset},
+    version::{RubyInterpreter, Version},
 };
-#[cfg(feature = "command_api")]
-use std::path::PathBuf;
 use zed_extension_api::{self as zed};
 
 #[derive(Clone, Debug)]
@@ -23,6 +31,7 @@ pub struct LspBinarySettings {
     #[allow(dead_code)]
     pub path: Option<String>,
     pub arguments: Option<Vec<String>>,
+    pub env: Option<HashMap<String, String>>,
 }
 
 pub trait WorktreeLike {
@@ -32,8 +41,10 @@ pub trait WorktreeLike {
     fn shell_env(&self) -> Vec<(String, String)>;
     fn read_text_file(&self, path: &str) -> Result<String, String>;
     fn lsp_binary_settings(&self, server_id: &str) -> Result<Option<LspBinarySettings>, String>;
-    #[cfg(any(test, not(feature = "command_api")))]
-    fn use_bundler(&self, server_id: &str) -> Result<Option<bool>, String>;
+    /// Returns the `lsp.<server_id>.settings` block.
+    fn server_settings(&self, server_id: &str) -> Result<Option<serde_json::Value>, String>;
+    /// Returns the `lsp.<server_id>.initialization_options` block.
+    fn initialization_options(&self, server_id: &str) -> Result<Option<serde_json::Value>, String>;
     fn which(&self, name: &str) -> Option<String>;
 }
 
@@ -55,19 +66,20 @@ impl WorktreeLike for zed::Worktree {
             Ok(lsp_settings) => Ok(lsp_settings.binary.map(|b| LspBinarySettings {
                 path: b.path,
                 arguments: b.arguments,
+                env: b.env,
             })),
             Err(e) => Err(e),
         }
     }
 
-    #[cfg(any(test, not(feature = "command_api")))]
-    fn use_bundler(&self, server_id: &str) -> Result<Option<bool>, String> {
-        zed::settings::LspSettings::for_worktree(server_id, self).map(|lsp_settings| {
-            lsp_settings
-                .settings
-                .as_ref()
-                .and_then(|settings| settings["use_bundler"].as_bool())
-        })
+    fn server_settings(&self, server_id: &str) -> Result<Option<serde_json::Value>, String> {
+        zed::settings::LspSettings::for_worktree(server_id, self)
+            .map(|lsp_settings| lsp_settings.settings)
+    }
+
+    fn initialization_options(&self, server_id: &str) -> Result<Option<serde_json::Value>, String> {
+        zed::settings::LspSettings::for_worktree(server_id, self)
+            .map(|lsp_settings| lsp_settings.initialization_options)
     }
 
     fn which(&self, name: &str) -> Option<String> {
@@ -75,13 +87,99 @@ impl WorktreeLike for zed::Worktree {
     }
 }
 
+/// Extension-side services that are not tied to a worktree.
+///
+/// Keeping these behind a trait lets the binary resolution logic run against a
+/// fake host in tests instead of calling into Zed directly.
+pub trait ExtensionHost {
+    fn set_installation_status(&self, status: &zed::LanguageServerInstallationStatus);
+    fn current_dir(&self) -> Result<PathBuf, String>;
+    /// Returns `None` when the extension is not allowed to spawn processes.
+    fn command_executor(&self) -> Option<Box<dyn CommandExecutor>>;
+    fn node_binary_path(&self) -> Result<String, String>;
+    fn npm_package_latest_version(&self, package_name: &str) -> Result<String, String>;
+    fn npm_package_installed_version(&self, package_name: &str) -> Result<Option<String>, String>;
+    fn npm_install_package(&self, package_name: &str, version: &str) -> Result<(), String>;
+    /// Where [`Self::ruby_interpreter`] keeps the interpreter it detected.
+    fn ruby_interpreter_cache(&self) -> &OnceCell<Result<RubyInterpreter, String>>;
+
+    /// Detects the `ruby` interpreter once per host, so that a launch runs
+    /// `ruby --version` at most once. Returns `None` when the extension is not
+    /// allowed to spawn processes.
+    fn ruby_interpreter(&self, envs: &[(&str, &str)]) -> Option<Result<RubyInterpreter, String>> {
+        let executor = self.command_executor()?;
+        let ruby = self.ruby_interpreter_cache().get_or_init(|| {
+            RubyInterpreter::detect(envs, executor.as_ref()).map_err(|e| format!("{e:#}"))
+        });
+        Some(ruby.clone())
+    }
+}
+
+/// The `ExtensionHost` backed by the Zed extension API for a single language server.
+pub struct ZedHost<'a> {
+    language_server_id: &'a zed::LanguageServerId,
+    ruby_interpreter: OnceCell<Result<RubyInterpreter, String>>,
+}
+
+impl<'a> ZedHost<'a> {
+    pub fn new(language_server_id: &'a zed::LanguageServerId) -> Self {
+        Self {
+            language_server_id,
+            ruby_interpreter: OnceCell::new(),
+        }
+    }
+}
+
+impl ExtensionHost for ZedHost<'_> {
+    fn set_installation_status(&self, status: &zed::LanguageServerInstallationStatus) {
+        zed::set_language_server_installation_status(self.language_server_id, status);
+    }
+
+    fn current_dir(&self) -> Result<PathBuf, String> {
+        std::env::current_dir().map_err(|e| format!("Failed to get extension directory: {e:#}"))
+    }
+
+    fn command_executor(&self) -> Option<Box<dyn CommandExecutor>> {
+        #[cfg(feature = "command_api")]
+        {
+            Some(Box::new(RealCommandExecutor))
+        }
+
+        #[cfg(not(feature = "command_api"))]
+        {
+            None
+        }
+    }
+
+    fn node_binary_path(&self) -> Result<String, String> {
+        zed::node_binary_path()
+    }
+
+    fn ruby_interpreter_cache(&self) -> &OnceCell<Result<RubyInterpreter, String>> {
+        &self.ruby_interpreter
+    }
+
+    fn npm_package_latest_version(&self, package_name: &str) -> Result<String, String> {
+        zed::npm_package_latest_version(package_name)
+    }
+
+    fn npm_package_installed_version(&self, package_name: &str) -> Result<Option<String>, String> {
+        zed::npm_package_installed_version(package_name)
+    }
+
+    fn npm_install_package(&self, package_name: &str, version: &str) -> Result<(), String> {
+        zed::npm_install_package(package_name, version)
+    }
+}
+
 #[cfg(test)]
 pub struct FakeWorktree {
     root_path: String,
     shell_env: Vec<(String, String)>,
     files: HashMap<String, Result<String, String>>,
     lsp_binary_settings_map: HashMap<String, Result<Option<LspBinarySettings>, String>>,
-    use_bundler_map: HashMap<String, Result<Option<bool>, String>>,
+    server_settings_map: HashMap<String, serde_json::Value>,
+    initialization_options_map: HashMap<String, serde_json::Value>,
     which_map: HashMap<String, Option<String>>,
 }
 
@@ -93,7 +191,8 @@ impl FakeWorktree {
             shell_env: Vec::new(),
             files: HashMap::new(),
             lsp_binary_settings_map: HashMap::new(),
-            use_bundler_map: HashMap::new(),
+            server_settings_map: HashMap::new(),
+            initialization_options_map: HashMap::new(),
             which_map: HashMap::new(),
         }
     }
@@ -110,13 +209,38 @@ impl FakeWorktree {
         self.lsp_binary_settings_map.insert(server_id, settings);
     }
 
-    pub fn set_use_bundler(&mut self, server_id: String, value: Result<Option<bool>, String>) {
-        self.use_bundler_map.insert(server_id, value);
+    pub fn set_use_bundler(&mut self, server_id: String, value: bool) {
+        self.set_server_setting(server_id, "use_bundler", value.into());
+    }
+
+    pub fn set_activation(&mut self, server_id: String, value: &str) {
+        self.set_server_setting(server_id, "activation", value.into());
+    }
+
+    /// Sets one key of `lsp.<server_id>.settings`, keeping the others.
+    fn set_server_setting(&mut self, server_id: String, key: &str, value: serde_json::Value) {
+        let settings = self
+            .server_settings_map
+            .entry(server_id)
+            .or_insert_with(|| serde_json::json!({}));
+        settings[key] = value;
+    }
+
+    pub fn set_server_settings(&mut self, server_id: String, settings: serde_json::Value) {
+        self.server_settings_map.insert(server_id, settings);
+    }
+
+    pub fn set_initialization_options(&mut self, server_id: String, options: serde_json::Value) {
+        self.initialization_options_map.insert(server_id, options);
     }
 
     pub fn set_which(&mut self, name: String, result: Option<String>) {
         self.which_map.insert(name, result);
     }
+
+    pub fn set_shell_env(&mut self, shell_env: Vec<(String, String)>) {
+        self.shell_env = shell_env;
+    }
 }
 
 #[cfg(test)]
@@ -143,11 +267,12 @@ impl WorktreeLike for FakeWorktree {
             .unwrap_or(Ok(None))
     }
 
-    fn use_bundler(&self, server_id: &str) -> Result<Option<bool>, String> {
-        self.use_bundler_map
-            .get(server_id)
-            .cloned()
-            .unwrap_or(Ok(None))
+    fn server_settings(&self, server_id: &str) -> Result<Option<serde_json::Value>, String> {
+        Ok(self.server_settings_map.get(server_id).cloned())
+    }
+
+    fn initialization_options(&self, server_id: &str) -> Result<Option<serde_json::Value>, String> {
+        Ok(self.initialization_options_map.get(server_id).cloned())
     }
 
     fn which(&self, name: &str) -> Option<String> {
@@ -155,11 +280,213 @@ impl WorktreeLike for FakeWorktree {
     }
 }
 
+#[cfg(test)]
+pub struct FakeHost {
+    current_dir: PathBuf,
+    executor: Option<ScriptedCommandExecutor>,
+    statuses: RefCell<Vec<String>>,
+    npm_latest_version: Result<String, String>,
+    npm_installed_version: RefCell<Option<String>>,
+    npm_install_result: Result<(), String>,
+    npm_calls: RefCell<Vec<String>>,
+    ruby_interpreter: OnceCell<Result<RubyInterpreter, String>>,
+}
+
+#[cfg(test)]
+impl FakeHost {
+    /// A host that cannot run commands, like the default command-free build.
+    pub fn command_free() -> Self {
+        Self {
+            current_dir: PathBuf::from("/extension"),
+            executor: None,
+            statuses: RefCell::new(Vec::new()),
+            npm_latest_version: Err("npm registry is not reachable".to_string()),
+            npm_installed_version: RefCell::new(None),
+            npm_install_result: Ok(()),
+            npm_calls: RefCell::new(Vec::new()),
+            ruby_interpreter: OnceCell::new(),
+        }
+    }
+
+    /// A host that replays commands from the given executor script.
+    pub fn with_executor(executor: ScriptedCommandExecutor) -> Self {
+        Self {
+            executor: Some(executor),
+            ..Self::command_free()
+        }
+    }
+
+    pub fn set_current_dir(&mut self, current_dir: PathBuf) {
+        self.current_dir = current_dir;
+    }
+
+    pub fn set_npm_latest_version(&mut self, version: Result<String, String>) {
+        self.npm_latest_version = version;
+    }
+
+    pub fn set_npm_installed_version(&mut self, version: Option<String>) {
+        self.npm_installed_version.replace(version);
+    }
+
+    pub fn set_npm_install_result(&mut self, result: Result<(), String>) {
+        self.npm_install_result = result;
+    }
+
+    pub fn statuses(&self) -> Vec<String> {
+        self.statuses.borrow().clone()
+    }
+
+    /// The npm operations performed so far, such as `latest <package>`.
+    pub fn npm_calls(&self) -> Vec<String> {
+        self.npm_calls.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+impl ExtensionHost for FakeHost {
+    fn set_installation_status(&self, status: &zed::LanguageServerInstallationStatus) {
+        self.statuses.borrow_mut().push(format!("{status:?}"));
+    }
+
+    fn current_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.current_dir.clone())
+    }
+
+    fn command_executor(&self) -> Option<Box<dyn CommandExecutor>> {
+        self.executor
+            .clone()
+            .map(|executor| Box::new(executor) as Box<dyn CommandExecutor>)
+    }
+
+    fn node_binary_path(&self) -> Result<String, String> {
+        Ok("/node/bin/node".to_string())
+    }
+
+    fn ruby_interpreter_cache(&self) -> &OnceCell<Result<RubyInterpreter, String>> {
+        &self.ruby_interpreter
+    }
+
+    fn npm_package_latest_version(&self, package_name: &str) -> Result<String, String> {
+        self.npm_calls
+            .borrow_mut()
+            .push(format!("latest {package_name}"));
+        self.npm_latest_version.clone()
+    }
+
+    fn npm_package_installed_version(&self, _package_name: &str) -> Result<Option<String>, String> {
+        Ok(self.npm_installed_version.borrow().clone())
+    }
+
+    fn npm_install_package(&self, package_name: &str, version: &str) -> Result<(), String> {
+        self.npm_calls
+            .borrow_mut()
+            .push(format!("install {package_name}@{version}"));
+        self.npm_install_result.clone()?;
+        self.npm_installed_version
+            .replace(Some(version.to_string()));
+        Ok(())
+    }
+}
+
+/// Reports why a server declined to start and returns the message as the error.
+pub fn decline<H: ExtensionHost>(host: &H, message: String) -> String {
+    host.set_installation_status(&zed::LanguageServerInstallationStatus::Failed(
+        message.clone(),
+    ));
+    message
+}
+
+/// Where a server's gem was found, which decides how to replace a version the
+/// server does not support.
+#[derive(Clone, Copy, Debug)]
+pub enum GemSource<'a> {
+    Bundle,
+    Path(&'a str),
+    Gemset,
+}
+
 pub trait LanguageServer {
     const SERVER_ID: &str;
     const EXECUTABLE_NAME: &str;
     #[allow(dead_code)]
     const GEM_NAME: &str;
+    /// The `workspace/configuration` section the server reads its settings from,
+    /// for servers that request one.
+    const CONFIGURATION_SECTION: Option<&str> = None;
+    /// The server's own keys under `lsp.<id>.settings`, next to the shared ones.
+    type Settings: ExtensionSettings;
+
+    /// Reads the typed `lsp.<id>.settings`, collecting warnings for keys that
+    /// were misspelled or had the wrong type.
+    fn settings<T: WorktreeLike>(
+        server_id: &str,
+        worktree: &T,
+    ) -> zed::Result<Settings<Self::Settings>> {
+        let settings = worktree.server_settings(server_id)?;
+        Ok(Settings::load(
+            server_id,
+            settings.as_ref(),
+            Self::CONFIGURATION_SECTION.is_some(),
+        ))
+    }
+
+    /// The JSON schema of `lsp.<id>.settings`.
+    #[allow(dead_code)]
+    fn settings_schema() -> serde_json::Value {
+        settings::schema::<Self::Settings>(Self::SERVER_ID, Self::CONFIGURATION_SECTION.is_some())
+    }
+
+    /// What is wrong with `lsp.<id>.settings`: keys and values that were
+    /// ignored, or why the settings could not be read at all.
+    fn settings_warnings<T: WorktreeLike>(server_id: &str, worktree: &T) -> Vec<String> {
+        match Self::settings(server_id, worktree) {
+            Ok(settings) => settings.warnings,
+            Err(e) => vec![format!("lsp.{server_id}.settings could not be read: {e}")],
+        }
+    }
+
+    /// Clears the installation status once the server is about to start. The
+    /// server starts with invalid settings ignored, so they are listed by
+    /// `/ruby-doctor` and logged rather than reported as a failed installation.
+    fn finish_installation<T: WorktreeLike, H: ExtensionHost>(
+        &self,
+        server_id: &str,
+        worktree: &T,
+        host: &H,
+    ) {
+        let warnings = Self::settings_warnings(server_id, worktree);
+        if !warnings.is_empty() {
+            eprintln!(
+                "{server_id}: Ignored invalid settings, run /ruby-doctor for details: {}",
+                warnings.join("; ")
+            );
+        }
+        host.set_installation_status(&zed::LanguageServerInstallationStatus::None);
+    }
+
+    /// Applies the `env_file` setting and `lsp.<id>.binary.env` to the
+    /// environment the server starts with.
+    fn server_env<T: WorktreeLike, H: ExtensionHost>(
+        server_id: &str,
+        worktree: &T,
+        host: &H,
+        env: Vec<(String, String)>,
+    ) -> zed::Result<Vec<(String, String)>> {
+        let env_file = Self::settings(server_id, worktree)?.shared.env_file;
+        let binary_env = worktree
+            .lsp_binary_settings(server_id)?
+            .and_then(|binary_settings| binary_settings.env);
+
+        let overrides = EnvOverrides::load(worktree, env_file.as_deref(), binary_env.as_ref())
+            .map_err(|e| {
+                decline(
+                    host,
+                    format!("Invalid lsp.{server_id}.settings.env_file: {e}"),
+                )
+            })?;
+
+        Ok(overrides.apply(env))
+    }
 
     fn default_use_bundler() -> bool {
         true
@@ -169,125 +496,255 @@ pub trait LanguageServer {
         Vec::new()
     }
 
+    /// The arguments for a configured `binary.path`. By default its
+    /// `binary.arguments` replace the generated ones.
+    fn configured_binary_args<T: WorktreeLike>(
+        &self,
+        _worktree: &T,
+        arguments: Option<Vec<String>>,
+    ) -> Option<Vec<String>> {
+        arguments
+    }
+
+    /// Returns why the server does not apply to the project, or `None` when
+    /// it should start with `"activation": "auto"`.
+    fn inactive_reason(&self, _markers: &ProjectMarkers) -> Option<String> {
+        None
+    }
+
+    /// The oldest Ruby interpreter the server runs on.
+    fn minimum_ruby_version() -> Option<Version> {
+        None
+    }
+
+    /// The oldest gem release the server works with.
+    fn minimum_gem_version() -> Option<Version> {
+        None
+    }
+
+    /// The first gem release the server no longer works with.
+    fn maximum_gem_version() -> Option<Version> {
+        None
+    }
+
+    /// Explains how to replace a gem version that cannot run the server, or
+    /// returns `None` when it is supported.
+    fn unsupported_gem_version(version: &Version, source: GemSource) -> Option<String> {
+        let (server_id, gem) = (Self::SERVER_ID, Self::GEM_NAME);
+        let found = match source {
+            GemSource::Bundle => format!("the project bundle has {version}"),
+            GemSource::Path(path) => format!("{path} is {version}"),
+            GemSource::Gemset => format!("the extension-managed gem is {version}"),
+        };
+
+        if let Some(minimum) = Self::minimum_gem_version().filter(|minimum| version < minimum) {
+            let fix = match source {
+                GemSource::Bundle => format!("Upgrade {gem} in your Gemfile, or set lsp.{server_id}.settings.use_bundler to false to use the extension-managed gem."),
+                GemSource::Path(_) => format!("Upgrade {gem} in the project environment, or configure lsp.{server_id}.binary.path."),
+                GemSource::Gemset => format!("Newer {gem} releases need a newer Ruby. Upgrade Ruby, or configure lsp.{server_id}.binary.path."),
+            };
+            return Some(format!(
+                "{server_id} needs {gem} {minimum} or newer, but {found}. {fix}"
+            ));
+        }
+        if let Some(maximum) = Self::maximum_gem_version().filter(|maximum| version >= maximum) {
+            let fix = match source {
+                GemSource::Bundle => format!("Pin {gem} below {maximum} in your Gemfile, or set lsp.{server_id}.settings.use_bundler to false to use the extension-managed gem."),
+                GemSource::Path(_) | GemSource::Gemset => format!("Add {gem} below {maximum} to your Gemfile, or configure lsp.{server_id}.binary.path."),
+            };
+            return Some(format!(
+                "{server_id} does not support {gem} {maximum} or newer, but {found}. {fix}"
+            ));
+        }
+
+        None
+    }
+
+    /// Checks the version of the executable found on `PATH` against the
+    /// supported gem range. Servers without a range, hosts that may not run
+    /// commands and versions that cannot be read are not checked.
+    fn ensure_supported_path_version<T: WorktreeLike, H: ExtensionHost>(
+        &self,
+        path: &str,
+        worktree: &T,
+        host: &H,
+    ) -> zed::Result<()> {
+        if Self::minimum_gem_version().is_none() && Self::maximum_gem_version().is_none() {
+            return Ok(());
+        }
+        let Some(executor) = host.command_executor() else {
+            return Ok(());
+        };
+
+        // Run by name with the project environment, which finds the same
+        // executable as `which` and matches the declared capability.
+        let shell_env = worktree.shell_env();
+        let env_vars: Vec<(&str, &str)> = shell_env
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let version = executor
+            .execute(Self::EXECUTABLE_NAME, &["--version"], &env_vars)
+            .ok()
+            .filter(|output| output.status == Some(0))
+            .and_then(|output| {
+                let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+                stdout.split_whitespace().next().and_then(Version::parse)
+            });
+
+        match version
+            .and_then(|version| Self::unsupported_gem_version(&version, GemSource::Path(path)))
+        {
+            Some(reason) => Err(decline(host, reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails with an installation status explaining why the server declined
+    /// to start when its tooling is not configured for the project or the
+    /// project's Ruby is too old for it.
+    fn ensure_active<T: WorktreeLike, H: ExtensionHost>(
+        &self,
+        server_id: &str,
+        worktree: &T,
+        host: &H,
+    ) -> zed::Result<()> {
+        let activation = Self::settings(server_id, worktree)?
+            .shared
+            .activation
+            .unwrap_or_default();
+        if activation == Activation::Auto {
+            if let Some(reason) = self.inactive_reason(&ProjectMarkers::detect(worktree)) {
+                return Err(decline(
+                    host,
+                    format!(
+                        "{server_id} is not enabled for this project: {reason}. Set lsp.{server_id}.settings.activation to \"always\" to start it anyway."
+                    ),
+                ));
+            }
+        }
+
+        // The interpreter version can only be checked when the host may run
+        // `ruby`. An unparseable version is not treated as too old.
+        let Some(minimum) = Self::minimum_ruby_version() else {
+            return Ok(());
+        };
+        let shell_env = worktree.shell_env();
+        let env_vars: Vec<(&str, &str)> = shell_env
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let Some(Ok(ruby)) = host.ruby_interpreter(&env_vars) else {
+            return Ok(());
+        };
+
+        match ruby.version() {
+            Ok(version) if version < minimum => Err(decline(
+                host,
+                format!("{server_id} requires Ruby {minimum} or newer, but the project uses Ruby {version}."),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     fn language_server_command(
         &mut self,
         language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
     ) -> zed::Result<zed::Command> {
-        let binary = self.language_server_binary(language_server_id, worktree)?;
+        let host = ZedHost::new(language_server_id);
+        self.ensure_active(language_server_id.as_ref(), worktree, &host)?;
 
-        zed::set_language_server_installation_status(
-            language_server_id,
-            &zed::LanguageServerInstallationStatus::None,
-        );
+        let binary = self.language_server_binary(language_server_id, worktree, &host)?;
+        let env = Self::server_env(
+            language_server_id.as_ref(),
+            worktree,
+            &host,
+            binary.env.unwrap_or_default(),
+        )?;
+
+        self.finish_installation(language_server_id.as_ref(), worktree, &host);
 
         Ok(zed::Command {
             command: binary.path,
             args: binary.args.unwrap_or(self.get_executable_args(worktree)),
-            env: binary.env.unwrap_or_default(),
+            env,
         })
     }
 
+    /// The user's `lsp.<id>.settings`, without the extension's own keys, under
+    /// [`Self::CONFIGURATION_SECTION`]. Servers without a section get none.
+    fn workspace_configuration<T: WorktreeLike>(
+        server_id: &str,
+        worktree: &T,
+    ) -> zed::Result<Option<serde_json::Value>> {
+        let Some(section) = Self::CONFIGURATION_SECTION else {
+            return Ok(None);
+        };
+        let mut settings = worktree
+            .server_settings(server_id)?
+            .unwrap_or_else(|| serde_json::json!({}));
+        if let Some(settings) = settings.as_object_mut() {
+            let extension_keys = settings::extension_keys::<Self::Settings>();
+            settings.retain(|key, _| !extension_keys.contains(key));
+        }
+
+        Ok(Some(serde_json::json!({ section: settings })))
+    }
+
+    /// Adjusts the environment of a server launched from the extension gemset,
+    /// which does not inherit the project's `bundle exec` setup.
+    fn gemset_env<T: WorktreeLike>(
+        &self,
+        _worktree: &T,
+        env: Vec<(String, String)>,
+    ) -> Vec<(String, String)> {
+        env
+    }
+
     fn language_server_binary(
         &self,
         language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
+        host: &ZedHost,
     ) -> zed::Result<LanguageServerBinary> {
-        #[cfg(not(feature = "command_api"))]
-        {
-            self.command_free_language_server_binary(language_server_id.as_ref(), worktree)
-        }
-
-        #[cfg(feature = "command_api")]
-        {
-            let lsp_settings =
-                zed::settings::LspSettings::for_worktree(language_server_id.as_ref(), worktree)?;
-
-            if let Some(binary_settings) = &lsp_settings.binary {
-                if let Some(path) = &binary_settings.path {
-                    return Ok(LanguageServerBinary {
-                        path: path.clone(),
-                        args: binary_settings.arguments.clone(),
-                        env: Some(worktree.shell_env()),
-                    });
-                }
-            }
-
-            let use_bundler = lsp_settings
-                .settings
-                .as_ref()
-                .and_then(|settings| settings["use_bundler"].as_bool())
-                .unwrap_or_else(Self::default_use_bundler);
-
-            if !use_bundler {
-                return self.try_find_on_path_or_extension_gemset(language_server_id, worktree);
-            }
-
-            let bundler = Bundler::new(PathBuf::from(worktree.root_path()), RealCommandExecutor);
-            let shell_env = worktree.shell_env();
-            let env_vars: Vec<(&str, &str)> = shell_env
-                .iter()
-                .map(|(key, value)| (key.as_str(), value.as_str()))
-                .collect();
-
-            match bundler.installed_gem_version(Self::GEM_NAME, &env_vars) {
-                Ok(_version) => {
-                    let bundle_path = worktree
-                        .which("bundle")
-                        .ok_or_else(|| "Unable to find 'bundle' command".to_string())?;
-
-                    Ok(LanguageServerBinary {
-                        path: bundle_path,
-                        args: Some(
-                            vec!["exec".into(), Self::EXECUTABLE_NAME.into()]
-                                .into_iter()
-                                .chain(self.get_executable_args(worktree))
-                                .collect(),
-                        ),
-                        env: Some(shell_env),
-                    })
-                }
-                Err(_e) => self.try_find_on_path_or_extension_gemset(language_server_id, worktree),
-            }
-        }
+        self.resolve_language_server_binary(language_server_id.as_ref(), worktree, host)
     }
 
-    #[cfg(any(test, not(feature = "command_api")))]
-    fn command_free_language_server_binary<T: WorktreeLike>(
+    /// Resolves the server binary in order: the configured `binary.path`, the
+    /// project bundle, the executable on `PATH` and finally the extension gemset.
+    ///
+    /// Hosts without a command executor skip probing Bundler and installing
+    /// gems: they launch through `bundle exec` whenever `bundle` is available.
+    fn resolve_language_server_binary<T: WorktreeLike, H: ExtensionHost>(
         &self,
         server_id: &str,
         worktree: &T,
+        host: &H,
     ) -> zed::Result<LanguageServerBinary> {
         if let Some(binary_settings) = worktree.lsp_binary_settings(server_id)? {
             if let Some(path) = binary_settings.path {
                 return Ok(LanguageServerBinary {
                     path,
-                    args: binary_settings.arguments,
+                    args: self.configured_binary_args(worktree, binary_settings.arguments),
                     env: Some(worktree.shell_env()),
                 });
             }
         }
 
-        let use_bundler = worktree
-            .use_bundler(server_id)?
+        let use_bundler = Self::settings(server_id, worktree)?
+            .shared
+            .use_bundler
             .unwrap_or_else(Self::default_use_bundler);
 
         if use_bundler {
-            if let Some(bundle_path) = worktree.which("bundle") {
-                return Ok(LanguageServerBinary {
-                    path: bundle_path,
-                    args: Some(
-                        vec!["exec".into(), Self::EXECUTABLE_NAME.into()]
-                            .into_iter()
-                            .chain(self.get_executable_args(worktree))
-                            .collect(),
-                    ),
-                    env: Some(worktree.shell_env()),
-                });
+            if let Some(binary) = self.bundled_language_server_binary(worktree, host)? {
+                return Ok(binary);
             }
         }
 
         if let Some(path) = worktree.which(Self::EXECUTABLE_NAME) {
+            self.ensure_supported_path_version(&path, worktree, host)?;
             return Ok(LanguageServerBinary {
                 path,
                 args: Some(self.get_executable_args(worktree)),
@@ -295,37 +752,104 @@ pub trait LanguageServer {
             });
         }
 
+        if host.command_executor().is_some() {
+            return self.extension_gemset_language_server_binary(worktree, host);
+        }
+
         Err(format!(
             "Unable to find 'bundle' or '{}' command for {server_id}. Install one in the project environment or configure lsp.{server_id}.binary.path.",
             Self::EXECUTABLE_NAME
         ))
     }
 
-    #[cfg(feature = "command_api")]
-    fn try_find_on_path_or_extension_gemset(
+    /// Returns a `bundle exec` binary when the server gem is part of the project
+    /// bundle, or `None` to continue with the next resolution step. A bundled
+    /// version outside the supported range fails instead of starting a server
+    /// that would exit right away.
+    fn bundled_language_server_binary<T: WorktreeLike, H: ExtensionHost>(
         &self,
-        language_server_id: &zed::LanguageServerId,
-        worktree: &zed::Worktree,
-    ) -> zed::Result<LanguageServerBinary> {
-        if let Some(path) = worktree.which(Self::EXECUTABLE_NAME) {
-            Ok(LanguageServerBinary {
-                path,
-                args: Some(self.get_executable_args(worktree)),
-                env: Some(worktree.shell_env()),
-            })
-        } else {
-            self.extension_gemset_language_server_binary(language_server_id, worktree)
+        worktree: &T,
+        host: &H,
+    ) -> zed::Result<Option<LanguageServerBinary>> {
+        let shell_env = worktree.shell_env();
+        let bundle_exec = |bundle_path: String| LanguageServerBinary {
+            path: bundle_path,
+            args: Some(
+                vec!["exec".into(), Self::EXECUTABLE_NAME.into()]
+                    .into_iter()
+                    .chain(self.get_executable_args(worktree))
+                    .collect(),
+            ),
+            env: Some(shell_env.clone()),
+        };
+
+        let Some(executor) = host.command_executor() else {
+            return Ok(worktree.which("bundle").map(bundle_exec));
+        };
+
+        let bundler = Bundler::new(PathBuf::from(worktree.root_path()), executor);
+        let env_vars: Vec<(&str, &str)> = shell_env
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+
+        match bundler.installed_gem_version(Self::GEM_NAME, &env_vars) {
+            Ok(version) => {
+                if let Some(reason) = Version::parse(&version)
+                    .and_then(|version| Self::unsupported_gem_version(&version, GemSource::Bundle))
+                {
+                    return Err(decline(host, reason));
+                }
+
+                let bundle_path = worktree
+                    .which("bundle")
+                    .ok_or_else(|| "Unable to find 'bundle' command".to_string())?;
+
+                Ok(Some(bundle_exec(bundle_path)))
+            }
+            Err(_e) => Ok(None),
         }
     }
 
-    #[cfg(feature = "command_api")]
-    fn extension_gemset_language_server_binary(
+    /// Checks the gem the extension installed against the supported range.
+    /// `gem install` picks the newest release for the project's Ruby, which
+    /// can still fall outside it. Reads the installed version when `version`
+    /// is not already known.
+    fn ensure_supported_gemset_version<H: ExtensionHost>(
         &self,
-        language_server_id: &zed::LanguageServerId,
-        worktree: &zed::Worktree,
+        gemset: &Gemset,
+        version: Option<String>,
+        host: &H,
+    ) -> zed::Result<()> {
+        if Self::minimum_gem_version().is_none() && Self::maximum_gem_version().is_none() {
+            return Ok(());
+        }
+        let version = match version {
+            Some(version) => Some(version),
+            None => gemset
+                .installed_gem_version(Self::GEM_NAME)
+                .map_err(|e| format!("{:#}", e))?,
+        };
+
+        match version
+            .as_deref()
+            .and_then(Version::parse)
+            .and_then(|version| Self::unsupported_gem_version(&version, GemSource::Gemset))
+        {
+            Some(reason) => Err(decline(host, reason)),
+            None => Ok(()),
+        }
+    }
+
+    fn extension_gemset_language_server_binary<T: WorktreeLike, H: ExtensionHost>(
+        &self,
+        worktree: &T,
+        host: &H,
     ) -> zed::Result<LanguageServerBinary> {
-        let base_dir = std::env::current_dir()
-            .map_err(|e| format!("Failed to get extension directory: {e:#}"))?;
+        let executor = host
+            .command_executor()
+            .ok_or_else(|| "Installing gems requires the command API".to_string())?;
+        let base_dir = host.current_dir()?;
 
         let worktree_shell_env = worktree.shell_env();
         let worktree_shell_env_vars: Vec<(&str, &str)> = worktree_shell_env
@@ -333,19 +857,13 @@ pub trait LanguageServer {
             .map(|(key, value)| (key.as_str(), value.as_str()))
             .collect();
 
-        let gem_home =
-            versioned_gem_home(&base_dir, &worktree_shell_env_vars, &RealCommandExecutor)
-                .map_err(|e| format!("{:#}", e))?;
+        let ruby = host
+            .ruby_interpreter(&worktree_shell_env_vars)
+            .ok_or_else(|| "Installing gems requires the command API".to_string())??;
+        let gem_home = interpreter_gem_home(&base_dir, &ruby);
 
-        let gemset = Gemset::new(
-            gem_home,
-            Some(&worktree_shell_env_vars),
-            Box::new(RealCommandExecutor),
-        );
-        zed::set_language_server_installation_status(
-            language_server_id,
-            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
-        );
+        let gemset = Gemset::new(gem_home, Some(&worktree_shell_env_vars), executor);
+        host.set_installation_status(&zed::LanguageServerInstallationStatus::CheckingForUpdate);
 
         match gemset.installed_gem_version(Self::GEM_NAME) {
             Ok(Some(version)) => {
@@ -353,8 +871,7 @@ pub trait LanguageServer {
                     .is_outdated_gem(Self::GEM_NAME)
                     .map_err(|e| format!("{:#}", e))?
                 {
-                    zed::set_language_server_installation_status(
-                        language_server_id,
+                    host.set_installation_status(
                         &zed::LanguageServerInstallationStatus::Downloading,
                     );
 
@@ -372,6 +889,9 @@ pub trait LanguageServer {
                             e
                         );
                     }
+                    self.ensure_supported_gemset_version(&gemset, None, host)?;
+                } else {
+                    self.ensure_supported_gemset_version(&gemset, Some(version), host)?;
                 }
 
                 let executable_path = gemset
@@ -381,18 +901,16 @@ pub trait LanguageServer {
                 Ok(LanguageServerBinary {
                     path: executable_path,
                     args: Some(self.get_executable_args(worktree)),
-                    env: Some(gemset.env().to_vec()),
+                    env: Some(self.gemset_env(worktree, gemset.env().to_vec())),
                 })
             }
             Ok(None) => {
-                zed::set_language_server_installation_status(
-                    language_server_id,
-                    &zed::LanguageServerInstallationStatus::Downloading,
-                );
+                host.set_installation_status(&zed::LanguageServerInstallationStatus::Downloading);
 
                 gemset
                     .install_gem(Self::GEM_NAME)
                     .map_err(|e| format!("{:#}", e))?;
+                self.ensure_supported_gemset_version(&gemset, None, host)?;
 
                 let executable_path = gemset
                     .gem_bin_path(Self::EXECUTABLE_NAME)
@@ -401,7 +919,7 @@ pub trait LanguageServer {
                 Ok(LanguageServerBinary {
                     path: executable_path,
                     args: Some(self.get_executable_args(worktree)),
-                    env: Some(gemset.env().to_vec()),
+                    env: Some(self.gemset_env(worktree, gemset.env().to_vec())),
                 })
             }
             Err(e) => Err(format!("{:#}", e)),
@@ -411,7 +929,13 @@ pub trait LanguageServer {
 
 #[cfg(test)]
 mod tests {
-    use super::{FakeWorktree, LanguageServer, WorktreeLike};
+    use std::collections::HashMap;
+
+    use super::{ExtensionHost, FakeHost, FakeWorktree, LanguageServer, WorktreeLike};
+    use crate::command_executor::ScriptedCommandExecutor;
+    use crate::language_servers::project::ProjectMarkers;
+    use crate::language_servers::settings::NoSettings;
+    use crate::version::Version;
 
     struct TestServer {}
 
@@ -425,10 +949,15 @@ mod tests {
         const SERVER_ID: &'static str = "test-server";
         const EXECUTABLE_NAME: &'static str = "test-exe";
         const GEM_NAME: &'static str = "test";
+        type Settings = NoSettings;
 
         fn get_executable_args<T: WorktreeLike>(&self, _worktree: &T) -> Vec<String> {
             vec!["--test-arg".into()]
         }
+
+        fn inactive_reason(&self, markers: &ProjectMarkers) -> Option<String> {
+            (!markers.has_file("Steepfile")).then(|| "no Steepfile found".to_string())
+        }
     }
 
     #[test]
@@ -459,11 +988,15 @@ mod tests {
     fn test_command_free_uses_bundle_exec_when_use_bundler_enabled() {
         let test_server = TestServer::new();
         let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
-        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), Ok(Some(true)));
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), true);
         mock_worktree.set_which("bundle".to_string(), Some("/bin/bundle".to_string()));
 
         let binary = test_server
-            .command_free_language_server_binary(TestServer::SERVER_ID, &mock_worktree)
+            .resolve_language_server_binary(
+                TestServer::SERVER_ID,
+                &mock_worktree,
+                &FakeHost::command_free(),
+            )
             .expect("command-free resolver should find bundle");
 
         assert_eq!(binary.path, "/bin/bundle");
@@ -481,12 +1014,16 @@ mod tests {
     fn test_command_free_falls_back_to_executable_when_bundle_missing() {
         let test_server = TestServer::new();
         let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
-        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), Ok(Some(true)));
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), true);
         mock_worktree.set_which("bundle".to_string(), None);
         mock_worktree.set_which("test-exe".to_string(), Some("/bin/test-exe".to_string()));
 
         let binary = test_server
-            .command_free_language_server_binary(TestServer::SERVER_ID, &mock_worktree)
+            .resolve_language_server_binary(
+                TestServer::SERVER_ID,
+                &mock_worktree,
+                &FakeHost::command_free(),
+            )
             .expect("command-free resolver should fall back to executable");
 
         assert_eq!(binary.path, "/bin/test-exe");
@@ -497,17 +1034,22 @@ mod tests {
     fn test_command_free_uses_configured_binary_before_bundler() {
         let test_server = TestServer::new();
         let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
-        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), Ok(Some(true)));
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), true);
         mock_worktree.add_lsp_binary_setting(
             TestServer::SERVER_ID.to_string(),
             Ok(Some(super::LspBinarySettings {
                 path: Some("/custom/test-exe".to_string()),
                 arguments: Some(vec!["--custom".to_string()]),
+                env: None,
             })),
         );
 
         let binary = test_server
-            .command_free_language_server_binary(TestServer::SERVER_ID, &mock_worktree)
+            .resolve_language_server_binary(
+                TestServer::SERVER_ID,
+                &mock_worktree,
+                &FakeHost::command_free(),
+            )
             .expect("command-free resolver should use configured binary");
 
         assert_eq!(binary.path, "/custom/test-exe");
@@ -518,11 +1060,15 @@ mod tests {
     fn test_command_free_uses_path_lookup_when_use_bundler_disabled() {
         let test_server = TestServer::new();
         let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
-        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), Ok(Some(false)));
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), false);
         mock_worktree.set_which("test-exe".to_string(), Some("/bin/test-exe".to_string()));
 
         let binary = test_server
-            .command_free_language_server_binary(TestServer::SERVER_ID, &mock_worktree)
+            .resolve_language_server_binary(
+                TestServer::SERVER_ID,
+                &mock_worktree,
+                &FakeHost::command_free(),
+            )
             .expect("command-free resolver should find server executable");
 
         assert_eq!(binary.path, "/bin/test-exe");
@@ -533,15 +1079,764 @@ mod tests {
     fn test_command_free_missing_executable_errors() {
         let test_server = TestServer::new();
         let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
-        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), Ok(Some(true)));
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), true);
         mock_worktree.set_which("bundle".to_string(), None);
         mock_worktree.set_which("test-exe".to_string(), None);
 
         let error = test_server
-            .command_free_language_server_binary(TestServer::SERVER_ID, &mock_worktree)
+            .resolve_language_server_binary(
+                TestServer::SERVER_ID,
+                &mock_worktree,
+                &FakeHost::command_free(),
+            )
             .expect_err("command-free resolver should fail when executable is missing");
 
         assert!(error.contains("Unable to find 'bundle' or 'test-exe' command"));
         assert!(error.contains("lsp.test-server.binary.path"));
     }
+
+    const RUBY_VERSION: &str = "ruby 3.3.0 (2023-12-25 revision 5124f9ac75) [arm64-darwin23]";
+
+    fn expect_gemset_lookup(executor: &ScriptedCommandExecutor, gem_list_output: &str) {
+        executor.expect_success("ruby", &["--version"], RUBY_VERSION);
+        executor.expect_success(
+            "gem",
+            &["list", "--norc", "--exact", "test"],
+            gem_list_output,
+        );
+    }
+
+    #[test]
+    fn test_uses_bundle_exec_when_gem_is_bundled() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_which("bundle".to_string(), Some("/bin/bundle".to_string()));
+        mock_worktree.set_shell_env(vec![("PATH".to_string(), "/bin".to_string())]);
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("bundle", &["info", "--version", "test"], "1.0.0");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect("resolver should use the project bundle");
+
+        assert_eq!(binary.path, "/bin/bundle");
+        assert_eq!(
+            binary.args,
+            Some(vec![
+                "exec".to_string(),
+                "test-exe".to_string(),
+                "--test-arg".to_string()
+            ])
+        );
+        assert_eq!(
+            binary.env,
+            Some(vec![("PATH".to_string(), "/bin".to_string())])
+        );
+        assert!(host.statuses().is_empty());
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_bundled_gem_without_bundle_command_errors() {
+        let test_server = TestServer::new();
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("bundle", &["info", "--version", "test"], "1.0.0");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect_err("resolver should fail without a bundle command");
+
+        assert_eq!(error, "Unable to find 'bundle' command");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_falls_back_to_path_when_gem_is_not_bundled() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_which("bundle".to_string(), Some("/bin/bundle".to_string()));
+        mock_worktree.set_which("test-exe".to_string(), Some("/bin/test-exe".to_string()));
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_failure(
+            "bundle",
+            &["info", "--version", "test"],
+            1,
+            "Could not find gem 'test'.",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect("resolver should fall back to the executable on PATH");
+
+        assert_eq!(binary.path, "/bin/test-exe");
+        assert_eq!(binary.args, Some(vec!["--test-arg".to_string()]));
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_skips_bundler_probe_when_use_bundler_disabled() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), false);
+        mock_worktree.set_which("test-exe".to_string(), Some("/bin/test-exe".to_string()));
+        let executor = ScriptedCommandExecutor::new();
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect("resolver should find the executable on PATH");
+
+        assert_eq!(binary.path, "/bin/test-exe");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_configured_binary_runs_no_commands() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.add_lsp_binary_setting(
+            TestServer::SERVER_ID.to_string(),
+            Ok(Some(super::LspBinarySettings {
+                path: Some("/custom/test-exe".to_string()),
+                arguments: None,
+                env: None,
+            })),
+        );
+        let executor = ScriptedCommandExecutor::new();
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect("resolver should use configured binary");
+
+        assert_eq!(binary.path, "/custom/test-exe");
+        assert_eq!(binary.args, None);
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_uses_installed_extension_gem() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), false);
+        let executor = ScriptedCommandExecutor::new();
+        expect_gemset_lookup(&executor, "test (1.0.0)");
+        executor.expect_success("gem", &["outdated", "--norc"], "other (1.0.0 < 1.1.0)");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect("resolver should use the extension gemset");
+
+        assert!(binary.path.starts_with("/extension/gems/"));
+        assert!(binary.path.ends_with("/bin/test-exe"));
+        assert_eq!(binary.args, Some(vec!["--test-arg".to_string()]));
+        let env = binary.env.expect("gemset binary should carry an env");
+        assert!(env.iter().any(|(key, _)| key == "GEM_PATH"));
+        assert_eq!(
+            host.statuses(),
+            vec!["LanguageServerInstallationStatus::CheckingForUpdate"]
+        );
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_installs_missing_extension_gem() {
+        let test_server = TestServer::new();
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_failure("bundle", &["info", "--version", "test"], 7, "no Gemfile");
+        expect_gemset_lookup(&executor, "");
+        executor.expect_success(
+            "gem",
+            &[
+                "install",
+                "--norc",
+                "--no-user-install",
+                "--no-format-executable",
+                "--no-document",
+                "test",
+            ],
+            "Successfully installed test-1.0.0",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect("resolver should install the gem");
+
+        assert!(binary.path.ends_with("/bin/test-exe"));
+        assert_eq!(
+            host.statuses(),
+            vec![
+                "LanguageServerInstallationStatus::CheckingForUpdate",
+                "LanguageServerInstallationStatus::Downloading"
+            ]
+        );
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_updates_outdated_extension_gem() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), false);
+        let executor = ScriptedCommandExecutor::new();
+        expect_gemset_lookup(&executor, "test (1.0.0)");
+        executor.expect_success("gem", &["outdated", "--norc"], "test (1.0.0 < 1.1.0)");
+        executor.expect_success("gem", &["update", "--norc", "test"], "Gems updated: test");
+        executor.expect_failure(
+            "gem",
+            &["uninstall", "--norc", "test", "--version", "1.0.0"],
+            1,
+            "gem \"test\" is not installed",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect("a failed uninstall should not fail resolution");
+
+        assert!(binary.path.ends_with("/bin/test-exe"));
+        assert_eq!(
+            host.statuses(),
+            vec![
+                "LanguageServerInstallationStatus::CheckingForUpdate",
+                "LanguageServerInstallationStatus::Downloading"
+            ]
+        );
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_extension_gem_install_failure_errors() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), false);
+        let executor = ScriptedCommandExecutor::new();
+        expect_gemset_lookup(&executor, "");
+        executor.expect_failure(
+            "gem",
+            &[
+                "install",
+                "--norc",
+                "--no-user-install",
+                "--no-format-executable",
+                "--no-document",
+                "test",
+            ],
+            1,
+            "network unreachable",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect_err("resolver should surface install failures");
+
+        assert!(error.contains("Failed to install gem 'test'"), "{error}");
+        assert!(error.contains("network unreachable"), "{error}");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_ruby_version_failure_errors() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_use_bundler(TestServer::SERVER_ID.to_string(), false);
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_failure("ruby", &["--version"], 127, "ruby: command not found");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect_err("resolver should fail without Ruby");
+
+        assert!(error.contains("Ruby version check failed with status 127"));
+        assert!(host.statuses().is_empty());
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_settings_error_is_propagated() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.add_lsp_binary_setting(
+            TestServer::SERVER_ID.to_string(),
+            Err("invalid settings".to_string()),
+        );
+        let executor = ScriptedCommandExecutor::new();
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = test_server
+            .resolve_language_server_binary(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect_err("resolver should propagate settings errors");
+
+        assert_eq!(error, "invalid settings");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_ensure_active_declines_without_project_tooling() {
+        let test_server = TestServer::new();
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        let host = FakeHost::command_free();
+
+        let error = test_server
+            .ensure_active(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect_err("server should decline to start");
+
+        assert!(error.contains("test-server is not enabled for this project: no Steepfile found"));
+        assert!(error.contains("lsp.test-server.settings.activation"));
+        assert_eq!(host.statuses().len(), 1);
+        assert!(host.statuses()[0].contains("Failed"));
+    }
+
+    #[test]
+    fn test_ensure_active_with_project_tooling() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.add_file("Steepfile".to_string(), Ok(String::new()));
+        let host = FakeHost::command_free();
+
+        assert!(test_server
+            .ensure_active(TestServer::SERVER_ID, &mock_worktree, &host)
+            .is_ok());
+        assert!(host.statuses().is_empty());
+    }
+
+    #[test]
+    fn test_ensure_active_always_skips_detection() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_activation(TestServer::SERVER_ID.to_string(), "always");
+        let host = FakeHost::command_free();
+
+        assert!(test_server
+            .ensure_active(TestServer::SERVER_ID, &mock_worktree, &host)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_host_detects_ruby_interpreter_once() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success(
+            "ruby",
+            &["--version"],
+            "ruby 3.3.0 (2023-12-25) [x86_64-linux]\n",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        let first = host.ruby_interpreter(&[]).expect("host can run ruby");
+        let second = host.ruby_interpreter(&[]).expect("host can run ruby");
+
+        executor.assert_finished();
+        assert_eq!(first, second);
+        assert_eq!(
+            first.and_then(|ruby| ruby.version().map_err(|e| e.to_string())),
+            Ok(Version::parse("3.3.0").unwrap())
+        );
+        assert!(FakeHost::command_free().ruby_interpreter(&[]).is_none());
+    }
+
+    #[test]
+    fn test_ensure_active_treats_invalid_activation_as_auto() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_activation(TestServer::SERVER_ID.to_string(), "never");
+        let host = FakeHost::command_free();
+
+        let error = test_server
+            .ensure_active(TestServer::SERVER_ID, &mock_worktree, &host)
+            .expect_err("an invalid activation should fall back to auto");
+
+        assert!(error.contains("is not enabled for this project"), "{error}");
+        assert!(TestServer::settings(TestServer::SERVER_ID, &mock_worktree)
+            .expect("settings should load")
+            .warnings[0]
+            .starts_with("lsp.test-server.settings.activation: unknown variant `never`"));
+    }
+
+    #[test]
+    fn test_finish_installation_clears_status() {
+        let test_server = TestServer::new();
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        let host = FakeHost::command_free();
+
+        test_server.finish_installation(TestServer::SERVER_ID, &mock_worktree, &host);
+
+        assert_eq!(
+            host.statuses(),
+            vec!["LanguageServerInstallationStatus::None"]
+        );
+    }
+
+    #[test]
+    fn test_finish_installation_does_not_fail_on_settings_warnings() {
+        let test_server = TestServer::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_server_settings(
+            TestServer::SERVER_ID.to_string(),
+            serde_json::json!({ "useBundler": true }),
+        );
+        let host = FakeHost::command_free();
+
+        test_server.finish_installation(TestServer::SERVER_ID, &mock_worktree, &host);
+
+        assert_eq!(
+            host.statuses(),
+            vec!["LanguageServerInstallationStatus::None".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_server_env_applies_env_file_and_binary_env() {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_server_settings(
+            TestServer::SERVER_ID.to_string(),
+            serde_json::json!({ "env_file": ".env.test" }),
+        );
+        mock_worktree.add_file(
+            ".env.test".to_string(),
+            Ok("CACHE_ROOT=/tmp/file\nDEBUG=1".to_string()),
+        );
+        mock_worktree.add_lsp_binary_setting(
+            TestServer::SERVER_ID.to_string(),
+            Ok(Some(super::LspBinarySettings {
+                path: None,
+                arguments: None,
+                env: Some(HashMap::from([
+                    ("CACHE_ROOT".to_string(), "/tmp/settings".to_string()),
+                    ("RUBYOPT".to_string(), String::new()),
+                ])),
+            })),
+        );
+        let host = FakeHost::command_free();
+
+        let env = TestServer::server_env(
+            TestServer::SERVER_ID,
+            &mock_worktree,
+            &host,
+            vec![
+                ("PATH".to_string(), "/usr/bin".to_string()),
+                ("RUBYOPT".to_string(), "-W0".to_string()),
+            ],
+        )
+        .expect("env should resolve");
+
+        assert_eq!(
+            env,
+            vec![
+                ("PATH".to_string(), "/usr/bin".to_string()),
+                ("CACHE_ROOT".to_string(), "/tmp/settings".to_string()),
+                ("DEBUG".to_string(), "1".to_string()),
+            ]
+        );
+        assert!(host.statuses().is_empty());
+    }
+
+    #[test]
+    fn test_server_env_declines_unreadable_env_file() {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_server_settings(
+            TestServer::SERVER_ID.to_string(),
+            serde_json::json!({ "env_file": ".env.test" }),
+        );
+        let host = FakeHost::command_free();
+
+        let result =
+            TestServer::server_env(TestServer::SERVER_ID, &mock_worktree, &host, Vec::new());
+
+        let message = "Invalid lsp.test-server.settings.env_file: unable to read .env.test: File not found in mock: .env.test";
+        assert_eq!(result, Err(message.to_string()));
+        assert_eq!(
+            host.statuses(),
+            vec![format!(
+                "LanguageServerInstallationStatus::Failed({message:?})"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_workspace_configuration_without_section() {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_server_settings(
+            TestServer::SERVER_ID.to_string(),
+            serde_json::json!({ "diagnostics": true }),
+        );
+
+        assert_eq!(
+            TestServer::workspace_configuration(TestServer::SERVER_ID, &mock_worktree),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_workspace_configuration_strips_extension_settings() {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_server_settings(
+            SectionedServer::SERVER_ID.to_string(),
+            serde_json::json!({
+                "use_bundler": false,
+                "activation": "always",
+                "diagnostics": true,
+                "logLevel": "debug"
+            }),
+        );
+
+        assert_eq!(
+            SectionedServer::workspace_configuration(SectionedServer::SERVER_ID, &mock_worktree),
+            Ok(Some(serde_json::json!({
+                "sectioned": { "diagnostics": true, "logLevel": "debug" }
+            })))
+        );
+    }
+
+    #[test]
+    fn test_workspace_configuration_without_settings() {
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        assert_eq!(
+            SectionedServer::workspace_configuration(SectionedServer::SERVER_ID, &mock_worktree),
+            Ok(Some(serde_json::json!({ "sectioned": {} })))
+        );
+    }
+
+    struct SectionedServer {}
+
+    impl LanguageServer for SectionedServer {
+        const SERVER_ID: &'static str = "sectioned-server";
+        const EXECUTABLE_NAME: &'static str = "sectioned";
+        const GEM_NAME: &'static str = "sectioned";
+        const CONFIGURATION_SECTION: Option<&'static str> = Some("sectioned");
+        type Settings = NoSettings;
+    }
+
+    struct VersionedServer {}
+
+    impl LanguageServer for VersionedServer {
+        const SERVER_ID: &'static str = "versioned-server";
+        const EXECUTABLE_NAME: &'static str = "versioned";
+        const GEM_NAME: &'static str = "versioned";
+        type Settings = NoSettings;
+
+        fn minimum_gem_version() -> Option<Version> {
+            Version::parse("1.53")
+        }
+
+        fn maximum_gem_version() -> Option<Version> {
+            Version::parse("2")
+        }
+    }
+
+    fn versioned_worktree() -> FakeWorktree {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_which("bundle".to_string(), Some("/bin/bundle".to_string()));
+        mock_worktree
+    }
+
+    #[test]
+    fn test_bundled_gem_within_supported_versions() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("bundle", &["info", "--version", "versioned"], "1.60.2");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = VersionedServer {}
+            .resolve_language_server_binary(
+                VersionedServer::SERVER_ID,
+                &versioned_worktree(),
+                &host,
+            )
+            .expect("supported bundled version should start");
+
+        assert_eq!(binary.path, "/bin/bundle");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_bundled_gem_too_old() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("bundle", &["info", "--version", "versioned"], "1.50.0");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = VersionedServer {}
+            .resolve_language_server_binary(
+                VersionedServer::SERVER_ID,
+                &versioned_worktree(),
+                &host,
+            )
+            .expect_err("old bundled version should not start");
+
+        assert!(error.contains("needs versioned 1.53 or newer"), "{error}");
+        assert!(error.contains("has 1.50.0"), "{error}");
+        assert!(
+            error.contains("Upgrade versioned in your Gemfile"),
+            "{error}"
+        );
+        assert_eq!(
+            host.statuses(),
+            vec![format!(
+                "LanguageServerInstallationStatus::Failed({error:?})"
+            )]
+        );
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_bundled_gem_too_new() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("bundle", &["info", "--version", "versioned"], "2.0.0");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = VersionedServer {}
+            .resolve_language_server_binary(
+                VersionedServer::SERVER_ID,
+                &versioned_worktree(),
+                &host,
+            )
+            .expect_err("bundled version at the maximum should not start");
+
+        assert!(error.contains("Pin versioned below 2"), "{error}");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_bundled_gem_version_check_can_be_bypassed() {
+        let mut mock_worktree = versioned_worktree();
+        mock_worktree.set_use_bundler(VersionedServer::SERVER_ID.to_string(), false);
+        mock_worktree.set_which("versioned".to_string(), Some("/bin/versioned".to_string()));
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("versioned", &["--version"], "1.60.2\n");
+
+        let binary = VersionedServer {}
+            .resolve_language_server_binary(
+                VersionedServer::SERVER_ID,
+                &mock_worktree,
+                &FakeHost::with_executor(executor.clone()),
+            )
+            .expect("use_bundler false should skip the bundle");
+
+        assert_eq!(binary.path, "/bin/versioned");
+        executor.assert_finished();
+    }
+
+    fn path_worktree() -> FakeWorktree {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_use_bundler(VersionedServer::SERVER_ID.to_string(), false);
+        mock_worktree.set_which("versioned".to_string(), Some("/bin/versioned".to_string()));
+        mock_worktree
+    }
+
+    #[test]
+    fn test_path_executable_within_supported_versions() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("versioned", &["--version"], "1.60.2\n");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = VersionedServer {}
+            .resolve_language_server_binary(VersionedServer::SERVER_ID, &path_worktree(), &host)
+            .expect("supported PATH version should start");
+
+        assert_eq!(binary.path, "/bin/versioned");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_path_executable_too_old() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("versioned", &["--version"], "1.50.0\n");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = VersionedServer {}
+            .resolve_language_server_binary(VersionedServer::SERVER_ID, &path_worktree(), &host)
+            .expect_err("old PATH version should not start");
+
+        assert_eq!(
+            error,
+            "versioned-server needs versioned 1.53 or newer, but /bin/versioned is 1.50.0. Upgrade versioned in the project environment, or configure lsp.versioned-server.binary.path."
+        );
+        assert_eq!(host.statuses().len(), 1);
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_path_executable_with_unreadable_version_starts() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_failure("versioned", &["--version"], 1, "invalid option");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = VersionedServer {}
+            .resolve_language_server_binary(VersionedServer::SERVER_ID, &path_worktree(), &host)
+            .expect("an unreadable version should not block the server");
+
+        assert_eq!(binary.path, "/bin/versioned");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_extension_gem_too_new() {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_use_bundler(VersionedServer::SERVER_ID.to_string(), false);
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("ruby", &["--version"], RUBY_VERSION);
+        executor.expect_success("gem", &["list", "--norc", "--exact", "versioned"], "");
+        executor.expect_success(
+            "gem",
+            &[
+                "install",
+                "--norc",
+                "--no-user-install",
+                "--no-format-executable",
+                "--no-document",
+                "versioned",
+            ],
+            "Successfully installed versioned-2.1.0",
+        );
+        executor.expect_success(
+            "gem",
+            &["list", "--norc", "--exact", "versioned"],
+            "versioned (2.1.0)",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = VersionedServer {}
+            .resolve_language_server_binary(VersionedServer::SERVER_ID, &mock_worktree, &host)
+            .expect_err("an installed gem at the maximum should not start");
+
+        assert_eq!(
+            error,
+            "versioned-server does not support versioned 2 or newer, but the extension-managed gem is 2.1.0. Add versioned below 2 to your Gemfile, or configure lsp.versioned-server.binary.path."
+        );
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_extension_gem_up_to_date_too_old() {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_use_bundler(VersionedServer::SERVER_ID.to_string(), false);
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("ruby", &["--version"], RUBY_VERSION);
+        executor.expect_success(
+            "gem",
+            &["list", "--norc", "--exact", "versioned"],
+            "versioned (1.40.0)",
+        );
+        executor.expect_success("gem", &["outdated", "--norc"], "");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = VersionedServer {}
+            .resolve_language_server_binary(VersionedServer::SERVER_ID, &mock_worktree, &host)
+            .expect_err("an old installed gem should not start");
+
+        assert!(
+            error.contains("the extension-managed gem is 1.40.0"),
+            "{error}"
+        );
+        assert!(error.contains("Upgrade Ruby"), "{error}");
+        executor.assert_finished();
+    }
 }
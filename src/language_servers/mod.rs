@@ -1,19 +1,156 @@
+mod doctor;
+mod environment;
 mod fuzzy_ruby_server;
 mod herb;
 mod kanayago;
+mod label;
 mod language_server;
+mod project;
 mod rubocop;
 mod ruby_lsp;
+mod settings;
 mod solargraph;
 mod sorbet;
+mod standard;
 mod steep;
+mod syntax_tree;
+mod typeprof;
 
+pub use doctor::{doctor_output, doctor_report, DOCTOR_COMMAND};
+pub use environment::EnvOverrides;
 pub use fuzzy_ruby_server::FuzzyRubyServer;
 pub use herb::Herb;
 pub use kanayago::Kanayago;
 pub use language_server::LanguageServer;
+pub use project::{GemfileLock, GEMFILE_LOCK};
 pub use rubocop::Rubocop;
 pub use ruby_lsp::RubyLsp;
 pub use solargraph::Solargraph;
 pub use sorbet::Sorbet;
+pub use standard::Standard;
 pub use steep::Steep;
+pub use syntax_tree::SyntaxTree;
+pub use typeprof::TypeProf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language_server::FakeWorktree;
+    use project::{ProjectMarkers, GEMFILE_LOCK};
+
+    /// Each server that only starts in configured projects starts with any of
+    /// its config files, or with its gem in the `Gemfile.lock`.
+    #[test]
+    fn test_inactive_reason_from_project_markers() {
+        type InactiveReason = Box<dyn Fn(&ProjectMarkers) -> Option<String>>;
+        let servers: [(&str, InactiveReason, &[&str], &str); 6] = [
+            (
+                Rubocop::SERVER_ID,
+                Box::new(|markers| Rubocop::new().inactive_reason(markers)),
+                &[project::RUBOCOP_CONFIG],
+                Rubocop::GEM_NAME,
+            ),
+            (
+                Sorbet::SERVER_ID,
+                Box::new(|markers| Sorbet::new().inactive_reason(markers)),
+                &[project::SORBET_CONFIG],
+                Sorbet::GEM_NAME,
+            ),
+            (
+                Standard::SERVER_ID,
+                Box::new(|markers| Standard::new().inactive_reason(markers)),
+                &[project::STANDARD_CONFIG],
+                Standard::GEM_NAME,
+            ),
+            (
+                Steep::SERVER_ID,
+                Box::new(|markers| Steep::new().inactive_reason(markers)),
+                &[project::STEEPFILE],
+                Steep::GEM_NAME,
+            ),
+            (
+                SyntaxTree::SERVER_ID,
+                Box::new(|markers| SyntaxTree::new().inactive_reason(markers)),
+                &[project::STREERC],
+                SyntaxTree::GEM_NAME,
+            ),
+            (
+                TypeProf::SERVER_ID,
+                Box::new(|markers| TypeProf::new().inactive_reason(markers)),
+                &[project::TYPEPROF_CONFIG, project::TYPEPROF_CONFIG_JSONC],
+                TypeProf::GEM_NAME,
+            ),
+        ];
+
+        for (server_id, inactive_reason, config_files, gem) in servers {
+            let worktree = FakeWorktree::new("/path/to/project".to_string());
+            let reason = inactive_reason(&ProjectMarkers::detect(&worktree))
+                .unwrap_or_else(|| panic!("{server_id} should not apply without config"));
+            for file in config_files {
+                assert!(reason.contains(file), "{server_id}: {reason}");
+            }
+
+            for file in config_files {
+                let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+                worktree.add_file(file.to_string(), Ok(String::new()));
+                assert_eq!(
+                    inactive_reason(&ProjectMarkers::detect(&worktree)),
+                    None,
+                    "{server_id} should apply with {file}"
+                );
+            }
+
+            let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+            worktree.add_file(
+                GEMFILE_LOCK.to_string(),
+                Ok(format!("DEPENDENCIES\n  {gem}\n")),
+            );
+            assert_eq!(
+                inactive_reason(&ProjectMarkers::detect(&worktree)),
+                None,
+                "{server_id} should apply with {gem} in the Gemfile.lock"
+            );
+        }
+    }
+
+    /// The reference schemas in `language_server_schemas/` are generated from
+    /// the typed settings. Run with `UPDATE_SCHEMAS=1` to rewrite them.
+    #[test]
+    fn test_generated_settings_schemas() {
+        let schemas = [
+            (
+                FuzzyRubyServer::SERVER_ID,
+                FuzzyRubyServer::settings_schema(),
+            ),
+            (Herb::SERVER_ID, Herb::settings_schema()),
+            (Kanayago::SERVER_ID, Kanayago::settings_schema()),
+            (Rubocop::SERVER_ID, Rubocop::settings_schema()),
+            (RubyLsp::SERVER_ID, RubyLsp::settings_schema()),
+            (Solargraph::SERVER_ID, Solargraph::settings_schema()),
+            (Sorbet::SERVER_ID, Sorbet::settings_schema()),
+            (Standard::SERVER_ID, Standard::settings_schema()),
+            (Steep::SERVER_ID, Steep::settings_schema()),
+            (SyntaxTree::SERVER_ID, SyntaxTree::settings_schema()),
+            (TypeProf::SERVER_ID, TypeProf::settings_schema()),
+        ];
+
+        for (server_id, schema) in schemas {
+            let path = format!(
+                "{}/language_server_schemas/{server_id}.json",
+                env!("CARGO_MANIFEST_DIR")
+            );
+            let expected = format!("{:#}\n", schema);
+
+            if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+                std::fs::write(&path, &expected).unwrap();
+                continue;
+            }
+
+            let generated = std::fs::read_to_string(&path).unwrap_or_default();
+            assert_eq!(
+                generated, expected,
+                "{path} is out of date, run the tests with UPDATE_SCHEMAS=1"
+            );
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use super::language_server::WorktreeLike;
+
+pub const GEMFILE_LOCK: &str = "Gemfile.lock";
+pub const SORBET_CONFIG: &str = "sorbet/config";
+pub const STEEPFILE: &str = "Steepfile";
+pub const STANDARD_CONFIG: &str = ".standard.yml";
+pub const RUBOCOP_CONFIG: &str = ".rubocop.yml";
+pub const STREERC: &str = ".streerc";
+pub const TYPEPROF_CONFIG: &str = "typeprof.conf.json";
+pub const TYPEPROF_CONFIG_JSONC: &str = "typeprof.conf.jsonc";
+pub const RSPEC_BINSTUB: &str = "bin/rspec";
+
+/// Files whose presence in the worktree root tells which tools a project uses.
+const MARKERS: &[&str] = &[
+    SORBET_CONFIG,
+    STEEPFILE,
+    STANDARD_CONFIG,
+    RUBOCOP_CONFIG,
+    STREERC,
+    TYPEPROF_CONFIG,
+    TYPEPROF_CONFIG_JSONC,
+    RSPEC_BINSTUB,
+];
+
+/// Controls whether a language server starts regardless of the project tooling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activation {
+    /// Start only when the project is configured for the server.
+    #[default]
+    Auto,
+    /// Always start the server.
+    Always,
+}
+
+/// The Ruby tooling detected in a worktree.
+#[derive(Debug, Default)]
+pub struct ProjectMarkers {
+    files: HashSet<&'static str>,
+    lockfile: Option<GemfileLock>,
+}
+
+impl ProjectMarkers {
+    pub fn detect<T: WorktreeLike>(worktree: &T) -> Self {
+        let files = MARKERS
+            .iter()
+            .copied()
+            .filter(|path| worktree.read_text_file(path).is_ok())
+            .collect();
+        let lockfile = worktree
+            .read_text_file(GEMFILE_LOCK)
+            .ok()
+            .map(|content| GemfileLock::parse(&content));
+
+        Self { files, lockfile }
+    }
+
+    pub fn has_file(&self, path: &str) -> bool {
+        self.files.contains(path)
+    }
+
+    /// Whether the project lists the gem as a direct dependency.
+    pub fn depends_on(&self, name: &str) -> bool {
+        self.lockfile
+            .as_ref()
+            .is_some_and(|lockfile| lockfile.is_dependency(name))
+    }
+}
+
+/// The gems recorded in a `Gemfile.lock`.
+#[derive(Debug, Default)]
+pub struct GemfileLock {
+    dependencies: HashSet<String>,
+    /// Every locked gem, including the ones other gems depend on.
+    specs: HashSet<String>,
+}
+
+impl GemfileLock {
+    pub fn parse(content: &str) -> Self {
+        let mut lockfile = Self::default();
+        let mut section = "";
+
+        for line in content.lines() {
+            if !line.starts_with(' ') {
+                section = line.trim();
+                continue;
+            }
+
+            if section == "DEPENDENCIES" {
+                let name = line
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .trim_end_matches('!');
+                if !name.is_empty() {
+                    lockfile.dependencies.insert(name.to_string());
+                }
+            } else if matches!(section, "GEM" | "GIT" | "PATH") {
+                // Specs are indented by four spaces, their own dependencies by six.
+                let is_spec = line.starts_with("    ") && !line.starts_with("     ");
+                if let Some(name) = line.split_whitespace().next().filter(|_| is_spec) {
+                    lockfile.specs.insert(name.to_string());
+                }
+            }
+        }
+
+        lockfile
+    }
+
+    pub fn is_dependency(&self, name: &str) -> bool {
+        self.dependencies.contains(name)
+    }
+
+    /// Whether the bundle includes the gem, directly or through another gem.
+    pub fn includes(&self, name: &str) -> bool {
+        self.dependencies.contains(name) || self.specs.contains(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language_servers::language_server::FakeWorktree;
+
+    const LOCKFILE: &str = "\
+GIT
+  remote: https://github.com/example/private_gem.git
+  revision: 0123456789abcdef
+  specs:
+    private_gem (0.1.0)
+
+GEM
+  remote: https://rubygems.org/
+  specs:
+    ast (2.4.2)
+    nokogiri (1.16.0-arm64-darwin)
+      racc (~> 1.4)
+    rubocop (1.60.2)
+      parser (>= 3.3.0.2)
+    standard (1.33.0)
+      rubocop (~> 1.60.2)
+
+PLATFORMS
+  arm64-darwin
+
+DEPENDENCIES
+  nokogiri (>= 1.16)
+  private_gem!
+  standard
+
+BUNDLED WITH
+   2.5.4
+";
+
+    #[test]
+    fn test_activation_deserialize() {
+        let activation = |value: &str| serde_json::from_value::<Activation>(value.into());
+
+        assert_eq!(activation("auto").ok(), Some(Activation::Auto));
+        assert_eq!(activation("always").ok(), Some(Activation::Always));
+        let error = activation("never").expect_err("unknown values should fail");
+        assert!(error.to_string().contains("`never`"), "{error}");
+    }
+
+    #[test]
+    fn test_lockfile_dependencies() {
+        let lockfile = GemfileLock::parse(LOCKFILE);
+
+        assert!(lockfile.is_dependency("standard"));
+        assert!(lockfile.is_dependency("nokogiri"));
+        assert!(lockfile.is_dependency("private_gem"));
+        assert!(!lockfile.is_dependency("rubocop"));
+        assert!(!lockfile.is_dependency("arm64-darwin"));
+    }
+
+    #[test]
+    fn test_lockfile_includes_locked_specs() {
+        let lockfile = GemfileLock::parse(LOCKFILE);
+
+        assert!(lockfile.includes("rubocop"));
+        assert!(lockfile.includes("private_gem"));
+        assert!(lockfile.includes("ast"));
+        assert!(!lockfile.includes("racc"));
+        assert!(!lockfile.includes("remote:"));
+        assert!(!lockfile.includes("arm64-darwin"));
+    }
+
+    #[test]
+    fn test_detect_markers() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(STEEPFILE.to_string(), Ok("target :app do\nend".to_string()));
+        worktree.add_file(RUBOCOP_CONFIG.to_string(), Err("not found".to_string()));
+        worktree.add_file(GEMFILE_LOCK.to_string(), Ok(LOCKFILE.to_string()));
+
+        let markers = ProjectMarkers::detect(&worktree);
+
+        assert!(markers.has_file(STEEPFILE));
+        assert!(!markers.has_file(RUBOCOP_CONFIG));
+        assert!(!markers.has_file(SORBET_CONFIG));
+        assert!(markers.depends_on("standard"));
+        assert!(!markers.depends_on("rubocop"));
+    }
+
+    #[test]
+    fn test_detect_without_lockfile() {
+        let worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        let markers = ProjectMarkers::detect(&worktree);
+
+        assert!(markers.lockfile.is_none());
+        assert!(!markers.depends_on("standard"));
+    }
+}
@@ -1,4 +1,11 @@
-use super::{language_server::WorktreeLike, LanguageServer};
+use super::{
+    language_server::{decline, ExtensionHost, LanguageServerBinary, WorktreeLike, ZedHost},
+    project::{ProjectMarkers, RUBOCOP_CONFIG},
+    settings::RubocopSettings,
+    LanguageServer,
+};
+use crate::version::Version;
+use zed_extension_api::{self as zed};
 
 pub struct Rubocop {}
 
@@ -6,9 +13,48 @@ impl LanguageServer for Rubocop {
     const SERVER_ID: &str = "rubocop";
     const EXECUTABLE_NAME: &str = "rubocop";
     const GEM_NAME: &str = "rubocop";
+    type Settings = RubocopSettings;
 
-    fn get_executable_args<T: WorktreeLike>(&self, _worktree: &T) -> Vec<String> {
-        vec!["--lsp".to_string()]
+    fn inactive_reason(&self, markers: &ProjectMarkers) -> Option<String> {
+        if markers.has_file(RUBOCOP_CONFIG) || markers.depends_on(Self::GEM_NAME) {
+            None
+        } else {
+            Some(format!(
+                "no {RUBOCOP_CONFIG} found and {} is not in the Gemfile.lock",
+                Self::GEM_NAME
+            ))
+        }
+    }
+
+    /// The first RuboCop release with the built-in `--lsp` server.
+    fn minimum_gem_version() -> Option<Version> {
+        Version::parse("1.53.0")
+    }
+
+    fn get_executable_args<T: WorktreeLike>(&self, worktree: &T) -> Vec<String> {
+        let mut args = vec!["--lsp".to_string()];
+        // Settings that cannot be read already failed in `resolve_binary`.
+        if let Ok(Some(config_path)) = configured_config_path(worktree) {
+            args.extend(["--config".to_string(), config_path]);
+        }
+        args
+    }
+
+    fn language_server_command(
+        &mut self,
+        language_server_id: &zed::LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> zed::Result<zed::Command> {
+        let host = ZedHost::new(language_server_id);
+        let binary = self.resolve_binary(language_server_id.as_ref(), worktree, &host)?;
+
+        self.finish_installation(language_server_id.as_ref(), worktree, &host);
+
+        Ok(zed::Command {
+            command: binary.path,
+            args: binary.args.unwrap_or(self.get_executable_args(worktree)),
+            env: binary.env.unwrap_or_default(),
+        })
     }
 }
 
@@ -16,11 +62,62 @@ impl Rubocop {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Resolves the binary and its environment once the project is known to
+    /// use RuboCop.
+    fn resolve_binary<T: WorktreeLike, H: ExtensionHost>(
+        &self,
+        server_id: &str,
+        worktree: &T,
+        host: &H,
+    ) -> zed::Result<LanguageServerBinary> {
+        self.ensure_active(server_id, worktree, host)?;
+
+        let settings = Self::settings(server_id, worktree)?.server;
+        let config_path = settings
+            .config_path
+            .clone()
+            .unwrap_or_else(|| RUBOCOP_CONFIG.to_string());
+        if settings.require_config == Some(true) && worktree.read_text_file(&config_path).is_err() {
+            return Err(decline(
+                host,
+                format!("{server_id} is not enabled for this project: no {config_path} found. Add one or set lsp.{server_id}.settings.require_config to false."),
+            ));
+        }
+
+        let mut binary = self.resolve_language_server_binary(server_id, worktree, host)?;
+        binary.env = Some(Self::server_env(
+            server_id,
+            worktree,
+            host,
+            binary.env.unwrap_or_default(),
+        )?);
+
+        Ok(binary)
+    }
+}
+
+/// Returns `lsp.rubocop.settings.config_path`, the config passed with `--config`.
+fn configured_config_path<T: WorktreeLike>(worktree: &T) -> zed::Result<Option<String>> {
+    Rubocop::settings(Rubocop::SERVER_ID, worktree).map(|settings| settings.server.config_path)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::language_servers::{language_server::FakeWorktree, LanguageServer, Rubocop};
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::command_executor::ScriptedCommandExecutor;
+    use crate::language_servers::language_server::{FakeHost, FakeWorktree, LspBinarySettings};
+    use zed_extension_api::serde_json::json;
+
+    fn worktree_with_rubocop_on_path() -> FakeWorktree {
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file(".rubocop.yml".to_string(), Ok(String::new()));
+        fake_worktree.set_use_bundler(Rubocop::SERVER_ID.to_string(), false);
+        fake_worktree.set_which("rubocop".to_string(), Some("/bin/rubocop".to_string()));
+        fake_worktree
+    }
 
     #[test]
     fn test_server_id() {
@@ -39,4 +136,143 @@ mod tests {
 
         assert_eq!(rubocop.get_executable_args(&mock_worktree), vec!["--lsp"]);
     }
+
+    #[test]
+    fn test_executable_args_with_config_path() {
+        let rubocop = Rubocop::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_server_settings(
+            Rubocop::SERVER_ID.to_string(),
+            json!({ "config_path": "config/rubocop.yml" }),
+        );
+
+        assert_eq!(
+            rubocop.get_executable_args(&mock_worktree),
+            vec!["--lsp", "--config", "config/rubocop.yml"]
+        );
+    }
+
+    #[test]
+    fn test_require_config_declines_without_config() {
+        let rubocop = Rubocop::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file(
+            "Gemfile.lock".to_string(),
+            Ok("DEPENDENCIES\n  rubocop\n".to_string()),
+        );
+        fake_worktree.set_server_settings(
+            Rubocop::SERVER_ID.to_string(),
+            json!({ "require_config": true }),
+        );
+        let host = FakeHost::command_free();
+
+        let error = rubocop
+            .resolve_binary(Rubocop::SERVER_ID, &fake_worktree, &host)
+            .expect_err("RuboCop should not start without its config");
+
+        assert!(error.contains("no .rubocop.yml found"), "{error}");
+        assert_eq!(host.statuses().len(), 1);
+    }
+
+    #[test]
+    fn test_require_config_accepts_configured_path() {
+        let rubocop = Rubocop::new();
+        let mut fake_worktree = worktree_with_rubocop_on_path();
+        fake_worktree.add_file("config/rubocop.yml".to_string(), Ok(String::new()));
+        fake_worktree.set_server_settings(
+            Rubocop::SERVER_ID.to_string(),
+            json!({
+                "use_bundler": false,
+                "require_config": true,
+                "config_path": "config/rubocop.yml"
+            }),
+        );
+
+        let binary = rubocop
+            .resolve_binary(
+                Rubocop::SERVER_ID,
+                &fake_worktree,
+                &FakeHost::command_free(),
+            )
+            .expect("configured config should satisfy require_config");
+
+        assert_eq!(binary.path, "/bin/rubocop");
+        assert_eq!(
+            binary.args,
+            Some(vec![
+                "--lsp".to_string(),
+                "--config".to_string(),
+                "config/rubocop.yml".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_binary_env_applies_to_rubocop() {
+        let rubocop = Rubocop::new();
+        let mut fake_worktree = worktree_with_rubocop_on_path();
+        fake_worktree.set_shell_env(vec![("PATH".to_string(), "/bin".to_string())]);
+        fake_worktree.add_lsp_binary_setting(
+            Rubocop::SERVER_ID.to_string(),
+            Ok(Some(LspBinarySettings {
+                env: Some(HashMap::from([(
+                    "RUBOCOP_CACHE_ROOT".to_string(),
+                    "/tmp/rubocop".to_string(),
+                )])),
+                ..Default::default()
+            })),
+        );
+
+        let binary = rubocop
+            .resolve_binary(
+                Rubocop::SERVER_ID,
+                &fake_worktree,
+                &FakeHost::command_free(),
+            )
+            .expect("RuboCop should resolve from PATH");
+
+        assert_eq!(
+            binary.env,
+            Some(vec![
+                ("PATH".to_string(), "/bin".to_string()),
+                ("RUBOCOP_CACHE_ROOT".to_string(), "/tmp/rubocop".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rejects_bundled_rubocop_without_lsp_support() {
+        let rubocop = Rubocop::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file(".rubocop.yml".to_string(), Ok(String::new()));
+        fake_worktree.set_which("bundle".to_string(), Some("/bin/bundle".to_string()));
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("bundle", &["info", "--version", "rubocop"], "1.50.2");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = rubocop
+            .resolve_binary(Rubocop::SERVER_ID, &fake_worktree, &host)
+            .expect_err("bundled RuboCop 1.50 has no --lsp");
+
+        assert!(error.contains("Upgrade rubocop in your Gemfile"), "{error}");
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_rejects_rubocop_on_path_without_lsp_support() {
+        let rubocop = Rubocop::new();
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("rubocop", &["--version"], "1.48.1\n");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = rubocop
+            .resolve_binary(Rubocop::SERVER_ID, &worktree_with_rubocop_on_path(), &host)
+            .expect_err("RuboCop 1.48 on PATH has no --lsp");
+
+        assert!(
+            error.contains("needs rubocop 1.53.0 or newer, but /bin/rubocop is 1.48.1"),
+            "{error}"
+        );
+        executor.assert_finished();
+    }
 }
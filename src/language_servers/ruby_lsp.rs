@@ -1,6 +1,16 @@
-use zed_extension_api::{self as zed};
+use zed_extension_api::{
+    self as zed,
+    serde_json::{json, Map, Value},
+};
 
-use super::LanguageServer;
+use super::{
+    environment::{bundle_gemfile, RubyVersionManager},
+    label::{method_label, plain_label, split_parameters, symbol_label, CompletionHighlights},
+    language_server::WorktreeLike,
+    project::{ProjectMarkers, RSPEC_BINSTUB, RUBOCOP_CONFIG, STANDARD_CONFIG, STREERC},
+    settings::NoSettings,
+    LanguageServer,
+};
 
 pub struct RubyLsp {}
 
@@ -8,6 +18,35 @@ impl LanguageServer for RubyLsp {
     const SERVER_ID: &str = "ruby-lsp";
     const EXECUTABLE_NAME: &str = "ruby-lsp";
     const GEM_NAME: &str = "ruby-lsp";
+    type Settings = NoSettings;
+
+    /// Points Ruby LSP at the project bundle when it runs from the extension
+    /// gemset, so `.ruby-lsp/Gemfile` is composed against the same Gemfile.
+    fn gemset_env<T: WorktreeLike>(
+        &self,
+        worktree: &T,
+        mut env: Vec<(String, String)>,
+    ) -> Vec<(String, String)> {
+        if !env.iter().any(|(key, _)| key == "BUNDLE_GEMFILE") {
+            if let Some(gemfile) = bundle_gemfile(worktree) {
+                env.push(("BUNDLE_GEMFILE".to_string(), gemfile));
+            }
+        }
+        env
+    }
+}
+
+/// Ruby LSP completes methods found by name alone as references.
+impl CompletionHighlights for RubyLsp {
+    fn is_method(&self, kind: &zed::lsp::CompletionKind) -> bool {
+        matches!(
+            kind,
+            zed::lsp::CompletionKind::Method
+                | zed::lsp::CompletionKind::Reference
+                | zed::lsp::CompletionKind::Function
+                | zed::lsp::CompletionKind::Constructor
+        )
+    }
 }
 
 impl RubyLsp {
@@ -16,137 +55,255 @@ impl RubyLsp {
     }
 
     pub fn label_for_completion(&self, completion: zed::lsp::Completion) -> Option<zed::CodeLabel> {
-        let zed::lsp::Completion {
-            label,
-            kind,
-            label_details,
-            ..
-        } = completion;
-        let kind = kind?;
-
-        let highlight_scope = match kind {
-            zed::lsp::CompletionKind::Class | zed::lsp::CompletionKind::Module => "type",
-            zed::lsp::CompletionKind::Constant if label == "nil" => "constant.builtin",
-            zed::lsp::CompletionKind::Constant
-                if label.starts_with("__") && label.ends_with("__") =>
-            {
-                "constant.builtin"
-            }
-            zed::lsp::CompletionKind::Constant => "constant",
-            zed::lsp::CompletionKind::Method
-            | zed::lsp::CompletionKind::Reference
-            | zed::lsp::CompletionKind::Function => "function.method",
-            zed::lsp::CompletionKind::Constructor => "function.method",
-            zed::lsp::CompletionKind::Keyword => "keyword",
-            zed::lsp::CompletionKind::Field if label.starts_with("@@") => "variable.special",
-            zed::lsp::CompletionKind::Field if label.starts_with('@') => "variable.special",
-            zed::lsp::CompletionKind::Field if label == "self" || label == "super" => {
-                "variable.special"
-            }
-            zed::lsp::CompletionKind::Variable => "variable",
-            zed::lsp::CompletionKind::Property => "property",
-            _ => return None,
+        let (detail, description) = match completion.label_details {
+            Some(label_details) => (label_details.detail, label_details.description),
+            None => (None, None),
         };
 
-        let label_len = label.len();
-        let mut spans = vec![zed::CodeLabelSpan::literal(
-            label,
-            Some(highlight_scope.to_string()),
-        )];
+        self.label_for_completion_details(completion.label, completion.kind?, detail, description)
+    }
 
-        if let Some(label_details) = label_details {
-            if let Some(detail) = label_details.detail {
-                spans.push(zed::CodeLabelSpan::literal(detail, None));
+    /// Builds the label from the completion's `labelDetails`, where Ruby LSP
+    /// reports a method's parameter list, such as `(a, b = 1)`, as the detail
+    /// and the defining file as the description.
+    fn label_for_completion_details(
+        &self,
+        label: String,
+        kind: zed::lsp::CompletionKind,
+        detail: Option<String>,
+        description: Option<String>,
+    ) -> Option<zed::CodeLabel> {
+        let mut code_label = if self.is_method(&kind) {
+            let (parameters, detail) = match detail {
+                Some(detail) if split_parameters(&detail).is_some() => (Some(detail), None),
+                detail => (None, detail),
+            };
+            let mut code_label = method_label(&label, parameters.as_deref(), None);
+            if let Some(detail) = detail {
+                code_label
+                    .spans
+                    .push(zed::CodeLabelSpan::literal(detail, None));
             }
+            code_label
+        } else {
+            plain_label(
+                &label,
+                self.name_highlight(&kind, &label)?,
+                detail.as_deref(),
+            )
+        };
 
-            if let Some(description) = label_details.description {
-                spans.push(zed::CodeLabelSpan::literal(" ", None));
-                spans.push(zed::CodeLabelSpan::literal(description, None));
-            }
+        if let Some(description) = description {
+            code_label
+                .spans
+                .push(zed::CodeLabelSpan::literal(" ", None));
+            code_label
+                .spans
+                .push(zed::CodeLabelSpan::literal(description, None));
         }
 
-        Some(zed::CodeLabel {
-            code: Default::default(),
-            spans,
-            filter_range: (0..label_len).into(),
-        })
+        Some(code_label)
     }
 
     pub fn label_for_symbol(&self, symbol: zed::lsp::Symbol) -> Option<zed::CodeLabel> {
-        let name = &symbol.name;
-
-        match symbol.kind {
-            zed::lsp::SymbolKind::Method => {
-                let code = format!("def {name}; end");
-                let filter_range = 0..name.len();
-                let display_range = 4..4 + name.len();
-
-                Some(zed::CodeLabel {
-                    code,
-                    spans: vec![zed::CodeLabelSpan::code_range(display_range)],
-                    filter_range: filter_range.into(),
-                })
-            }
-            zed::lsp::SymbolKind::Class | zed::lsp::SymbolKind::Module => {
-                let code = format!("class {name}; end");
-                let filter_range = 0..name.len();
-                let display_range = 6..6 + name.len();
-
-                Some(zed::CodeLabel {
-                    code,
-                    spans: vec![zed::CodeLabelSpan::code_range(display_range)],
-                    filter_range: filter_range.into(),
-                })
-            }
-            zed::lsp::SymbolKind::Constant => {
-                let code = name.clone();
-                let filter_range = 0..name.len();
-                let display_range = 0..name.len();
-
-                Some(zed::CodeLabel {
-                    code,
-                    spans: vec![zed::CodeLabelSpan::code_range(display_range)],
-                    filter_range: filter_range.into(),
-                })
-            }
-            _ => None,
+        symbol_label(&symbol)
+    }
+
+    pub fn language_server_initialization_options<T: WorktreeLike>(
+        &self,
+        server_id: &str,
+        worktree: &T,
+    ) -> zed::Result<Option<Value>> {
+        let (initialization_options, inferred) = self.initialization_options(server_id, worktree);
+        if !inferred.is_empty() {
+            eprintln!(
+                "{server_id}: inferred initialization options from the project: {}",
+                Value::Object(inferred)
+            );
         }
+
+        Ok(Some(initialization_options))
+    }
+
+    /// Describes the formatter, linters, test library and add-on settings Ruby
+    /// LSP starts with, and whether each was inferred from the project.
+    pub fn doctor_report<T: WorktreeLike>(&self, server_id: &str, worktree: &T) -> Vec<String> {
+        let (options, inferred) = self.initialization_options(server_id, worktree);
+
+        INFERRED_OPTIONS
+            .iter()
+            .filter_map(|key| {
+                let value = options.get(key)?;
+                let source = match inferred.get(*key) {
+                    Some(inferred) if inferred == value => "inferred from the project".to_string(),
+                    Some(_) => "partly inferred from the project".to_string(),
+                    None => format!("set in lsp.{server_id}.initialization_options"),
+                };
+                Some(format!("`{key}`: `{value}`, {source}"))
+            })
+            .collect()
     }
 
-    pub fn language_server_initialization_options(
+    /// The options Ruby LSP starts with, and the inferred defaults among them.
+    fn initialization_options<T: WorktreeLike>(
         &self,
-        language_server_id: &zed::LanguageServerId,
-        worktree: &zed::Worktree,
-    ) -> zed::Result<Option<zed::serde_json::Value>> {
-        let mut initialization_options =
-            zed::settings::LspSettings::for_worktree(language_server_id.as_ref(), worktree)
-                .ok()
-                .and_then(|lsp_settings| lsp_settings.initialization_options.clone())
-                .unwrap_or_else(|| zed::serde_json::json!({}));
+        server_id: &str,
+        worktree: &T,
+    ) -> (Value, Map<String, Value>) {
+        let mut initialization_options = worktree
+            .initialization_options(server_id)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| json!({}));
 
         let options_obj = initialization_options
             .as_object_mut()
             .expect("initialization_options must be an object");
 
+        merge_defaults(options_obj, environment_initialization_options(worktree));
+        let inferred = merge_defaults(
+            options_obj,
+            inferred_initialization_options(&ProjectMarkers::detect(worktree)),
+        );
+
         let enabled_features = options_obj
             .entry("enabledFeatures")
-            .or_insert_with(|| zed::serde_json::json!({}));
+            .or_insert_with(|| json!({}));
 
         // Workaround ruby-lsp upstream issue
         // https://github.com/zed-extensions/ruby/issues/38
         if let Some(features_obj) = enabled_features.as_object_mut() {
             features_obj
                 .entry("onTypeFormatting")
-                .or_insert(zed::serde_json::Value::Bool(false));
+                .or_insert(Value::Bool(false));
         }
 
-        Ok(Some(initialization_options))
+        (initialization_options, inferred)
+    }
+}
+
+/// Tells Ruby LSP which Ruby manager and Gemfile the extension resolved, so
+/// both agree on one interpreter and one bundle.
+fn environment_initialization_options<T: WorktreeLike>(worktree: &T) -> Map<String, Value> {
+    let mut options = Map::new();
+
+    if let Some(manager) = RubyVersionManager::detect(worktree) {
+        options.insert(
+            "rubyVersionManager".to_string(),
+            json!({ "identifier": manager.identifier() }),
+        );
     }
+    if let Some(gemfile) = bundle_gemfile(worktree) {
+        options.insert("bundleGemfile".to_string(), json!(gemfile));
+    }
+
+    options
+}
+
+/// The options [`inferred_initialization_options`] may set.
+const INFERRED_OPTIONS: [&str; 4] = ["formatter", "linters", "testLibrary", "addonSettings"];
+
+/// Picks the formatter, linters, test library and add-on settings that match
+/// the tools the project already uses, so Ruby LSP does not fall back to
+/// RuboCop in a Standard or Syntax Tree project.
+fn inferred_initialization_options(markers: &ProjectMarkers) -> Map<String, Value> {
+    let uses_standard = markers.has_file(STANDARD_CONFIG) || markers.depends_on("standard");
+    let uses_syntax_tree = markers.has_file(STREERC) || markers.depends_on("syntax_tree");
+    let uses_rubocop = markers.has_file(RUBOCOP_CONFIG) || markers.depends_on("rubocop");
+
+    let mut options = Map::new();
+
+    let (formatter, linters): (Option<&str>, &[&str]) = if uses_standard {
+        (Some("standard"), &["standard"])
+    } else if uses_syntax_tree && uses_rubocop {
+        (Some("syntax_tree"), &["rubocop"])
+    } else if uses_syntax_tree {
+        (Some("syntax_tree"), &[])
+    } else if uses_rubocop {
+        (Some("rubocop"), &["rubocop"])
+    } else {
+        (None, &[])
+    };
+    if let Some(formatter) = formatter {
+        options.insert("formatter".to_string(), json!(formatter));
+    }
+    if !linters.is_empty() {
+        options.insert("linters".to_string(), json!(linters));
+    }
+
+    let uses_rspec = ["rspec", "rspec-core", "rspec-rails"]
+        .iter()
+        .any(|gem| markers.depends_on(gem));
+    let test_library = if uses_rspec {
+        Some("rspec")
+    } else if markers.depends_on("test-unit") {
+        Some("test-unit")
+    } else if markers.depends_on("minitest") || markers.depends_on("rails") {
+        Some("minitest")
+    } else {
+        None
+    };
+    if let Some(test_library) = test_library {
+        options.insert("testLibrary".to_string(), json!(test_library));
+    }
+
+    if uses_rspec && markers.depends_on("ruby-lsp-rspec") && markers.has_file(RSPEC_BINSTUB) {
+        options.insert(
+            "addonSettings".to_string(),
+            json!({ "Ruby LSP RSpec": { "rspecCommand": RSPEC_BINSTUB } }),
+        );
+    }
+
+    options
+}
+
+/// Fills in `defaults` wherever `options` does not already set a value,
+/// descending into nested objects so a partial user object keeps the rest.
+/// Returns the defaults that were applied.
+fn merge_defaults(
+    options: &mut Map<String, Value>,
+    defaults: Map<String, Value>,
+) -> Map<String, Value> {
+    let mut applied = Map::new();
+    for (key, default) in defaults {
+        match (options.get_mut(&key), default) {
+            (None, default) => {
+                options.insert(key.clone(), default.clone());
+                applied.insert(key, default);
+            }
+            (Some(Value::Object(existing)), Value::Object(default)) => {
+                let nested = merge_defaults(existing, default);
+                if !nested.is_empty() {
+                    applied.insert(key, Value::Object(nested));
+                }
+            }
+            (Some(_), _) => {}
+        }
+    }
+    applied
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::language_servers::{language_server::FakeWorktree, LanguageServer, RubyLsp};
+    use super::*;
+    use crate::language_servers::{
+        label::render_label, language_server::FakeWorktree, project::GEMFILE_LOCK,
+    };
+
+    fn lockfile(dependencies: &[&str]) -> String {
+        let dependencies: Vec<String> = dependencies.iter().map(|gem| format!("  {gem}")).collect();
+        format!(
+            "GEM\n  specs:\n\nDEPENDENCIES\n{}\n",
+            dependencies.join("\n")
+        )
+    }
+
+    fn initialization_options(worktree: &FakeWorktree) -> Value {
+        RubyLsp::new()
+            .language_server_initialization_options(RubyLsp::SERVER_ID, worktree)
+            .expect("options should build")
+            .expect("options should be present")
+    }
 
     #[test]
     fn test_server_id() {
@@ -168,4 +325,347 @@ mod tests {
             vec![] as Vec<String>
         );
     }
+
+    #[test]
+    fn test_initialization_options_without_project_tooling() {
+        let worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        assert_eq!(
+            initialization_options(&worktree),
+            json!({ "enabledFeatures": { "onTypeFormatting": false } })
+        );
+    }
+
+    #[test]
+    fn test_initialization_options_infer_standard() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(
+            GEMFILE_LOCK.to_string(),
+            Ok(lockfile(&["minitest", "rubocop", "standard"])),
+        );
+
+        let options = initialization_options(&worktree);
+
+        assert_eq!(options["formatter"], json!("standard"));
+        assert_eq!(options["linters"], json!(["standard"]));
+        assert_eq!(options["testLibrary"], json!("minitest"));
+        assert!(options.get("addonSettings").is_none());
+    }
+
+    #[test]
+    fn test_initialization_options_infer_syntax_tree() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(STREERC.to_string(), Ok("--print-width=100".to_string()));
+
+        let options = initialization_options(&worktree);
+
+        assert_eq!(options["formatter"], json!("syntax_tree"));
+        assert!(options.get("linters").is_none());
+    }
+
+    #[test]
+    fn test_initialization_options_infer_syntax_tree_with_rubocop() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(STREERC.to_string(), Ok(String::new()));
+        worktree.add_file(RUBOCOP_CONFIG.to_string(), Ok(String::new()));
+
+        let options = initialization_options(&worktree);
+
+        assert_eq!(options["formatter"], json!("syntax_tree"));
+        assert_eq!(options["linters"], json!(["rubocop"]));
+    }
+
+    #[test]
+    fn test_initialization_options_infer_rspec_addon() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(
+            GEMFILE_LOCK.to_string(),
+            Ok(lockfile(&["rails", "rspec-rails", "ruby-lsp-rspec"])),
+        );
+        worktree.add_file(RSPEC_BINSTUB.to_string(), Ok(String::new()));
+
+        let options = initialization_options(&worktree);
+
+        assert_eq!(options["testLibrary"], json!("rspec"));
+        assert_eq!(
+            options["addonSettings"],
+            json!({ "Ruby LSP RSpec": { "rspecCommand": "bin/rspec" } })
+        );
+    }
+
+    #[test]
+    fn test_initialization_options_user_values_win() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(STANDARD_CONFIG.to_string(), Ok(String::new()));
+        worktree.add_file(
+            GEMFILE_LOCK.to_string(),
+            Ok(lockfile(&["rspec", "ruby-lsp-rspec"])),
+        );
+        worktree.add_file(RSPEC_BINSTUB.to_string(), Ok(String::new()));
+        worktree.set_initialization_options(
+            RubyLsp::SERVER_ID.to_string(),
+            json!({
+                "formatter": "rubocop",
+                "linters": [],
+                "addonSettings": { "Ruby LSP RSpec": { "debug": true } },
+                "enabledFeatures": { "onTypeFormatting": true }
+            }),
+        );
+
+        let options = initialization_options(&worktree);
+
+        assert_eq!(options["formatter"], json!("rubocop"));
+        assert_eq!(options["linters"], json!([]));
+        assert_eq!(options["testLibrary"], json!("rspec"));
+        assert_eq!(
+            options["addonSettings"],
+            json!({ "Ruby LSP RSpec": { "debug": true, "rspecCommand": "bin/rspec" } })
+        );
+        assert_eq!(options["enabledFeatures"]["onTypeFormatting"], json!(true));
+    }
+
+    #[test]
+    fn test_doctor_report_names_the_source() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file(
+            GEMFILE_LOCK.to_string(),
+            Ok(lockfile(&["minitest", "rubocop"])),
+        );
+        worktree.set_initialization_options(
+            RubyLsp::SERVER_ID.to_string(),
+            json!({ "formatter": "none" }),
+        );
+
+        assert_eq!(
+            RubyLsp::new().doctor_report(RubyLsp::SERVER_ID, &worktree),
+            vec![
+                "`formatter`: `\"none\"`, set in lsp.ruby-lsp.initialization_options",
+                "`linters`: `[\"rubocop\"]`, inferred from the project",
+                "`testLibrary`: `\"minitest\"`, inferred from the project",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_defaults_returns_applied_values() {
+        let mut options = json!({
+            "formatter": "rubocop",
+            "addonSettings": { "Ruby LSP RSpec": { "debug": true } }
+        });
+        let defaults = json!({
+            "formatter": "standard",
+            "linters": ["standard"],
+            "addonSettings": { "Ruby LSP RSpec": { "debug": false, "rspecCommand": "bin/rspec" } }
+        });
+
+        let applied = merge_defaults(
+            options.as_object_mut().unwrap(),
+            defaults.as_object().unwrap().clone(),
+        );
+
+        assert_eq!(
+            Value::Object(applied),
+            json!({
+                "linters": ["standard"],
+                "addonSettings": { "Ruby LSP RSpec": { "rspecCommand": "bin/rspec" } }
+            })
+        );
+    }
+
+    #[test]
+    fn test_initialization_options_include_ruby_environment() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_which(
+            "ruby".to_string(),
+            Some("/home/me/.rbenv/shims/ruby".to_string()),
+        );
+        worktree.add_file("Gemfile".to_string(), Ok(String::new()));
+
+        let options = initialization_options(&worktree);
+
+        assert_eq!(
+            options["rubyVersionManager"],
+            json!({ "identifier": "rbenv" })
+        );
+        assert_eq!(options["bundleGemfile"], json!("/path/to/project/Gemfile"));
+    }
+
+    #[test]
+    fn test_initialization_options_keep_user_ruby_environment() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.set_which("ruby".to_string(), Some("/usr/bin/ruby".to_string()));
+        worktree.add_file("Gemfile".to_string(), Ok(String::new()));
+        worktree.set_initialization_options(
+            RubyLsp::SERVER_ID.to_string(),
+            json!({
+                "rubyVersionManager": { "identifier": "custom" },
+                "bundleGemfile": "gemfiles/rails.gemfile"
+            }),
+        );
+
+        let options = initialization_options(&worktree);
+
+        assert_eq!(
+            options["rubyVersionManager"],
+            json!({ "identifier": "custom" })
+        );
+        assert_eq!(options["bundleGemfile"], json!("gemfiles/rails.gemfile"));
+    }
+
+    #[test]
+    fn test_gemset_env_sets_bundle_gemfile() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file("Gemfile".to_string(), Ok(String::new()));
+
+        let env = RubyLsp::new().gemset_env(&worktree, vec![("PATH".into(), "/bin".into())]);
+
+        assert_eq!(
+            env,
+            vec![
+                ("PATH".to_string(), "/bin".to_string()),
+                (
+                    "BUNDLE_GEMFILE".to_string(),
+                    "/path/to/project/Gemfile".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gemset_env_keeps_existing_bundle_gemfile() {
+        let mut worktree = FakeWorktree::new("/path/to/project".to_string());
+        worktree.add_file("Gemfile".to_string(), Ok(String::new()));
+        let env = vec![(
+            "BUNDLE_GEMFILE".to_string(),
+            "/elsewhere/Gemfile".to_string(),
+        )];
+
+        assert_eq!(RubyLsp::new().gemset_env(&worktree, env.clone()), env);
+    }
+
+    #[test]
+    fn test_completion_labels() {
+        use zed_extension_api::lsp::CompletionKind;
+
+        let cases = [
+            (
+                "fetch",
+                CompletionKind::Method,
+                Some("(key, default = nil)"),
+                Some("hash.rb"),
+            ),
+            (
+                "find_each",
+                CompletionKind::Method,
+                Some("(start: nil, batch_size: 1000, &block)"),
+                Some("batches.rb"),
+            ),
+            (
+                "save!",
+                CompletionKind::Method,
+                Some("()"),
+                Some("persistence.rb"),
+            ),
+            ("presence", CompletionKind::Method, None, None),
+            (
+                "run",
+                CompletionKind::Reference,
+                Some("(*args, **opts)"),
+                Some("cli.rb"),
+            ),
+            (
+                "puts",
+                CompletionKind::Function,
+                Some("(*objects)"),
+                Some("kernel.rbs"),
+            ),
+            (
+                "new",
+                CompletionKind::Constructor,
+                Some("(name)"),
+                Some("user.rb"),
+            ),
+            ("User", CompletionKind::Class, None, Some("user.rb")),
+            ("Comparable", CompletionKind::Module, None, None),
+            (
+                "VERSION",
+                CompletionKind::Constant,
+                None,
+                Some("version.rb"),
+            ),
+            ("nil", CompletionKind::Constant, None, None),
+            ("__FILE__", CompletionKind::Constant, None, None),
+            ("def", CompletionKind::Keyword, None, None),
+            ("@name", CompletionKind::Field, None, None),
+            ("@@count", CompletionKind::Field, None, None),
+            ("self", CompletionKind::Field, None, None),
+            ("user", CompletionKind::Variable, None, None),
+            ("email", CompletionKind::Property, None, None),
+            ("each", CompletionKind::Snippet, None, None),
+        ];
+
+        let ruby_lsp = RubyLsp::new();
+        let rendered: Vec<String> = cases
+            .into_iter()
+            .map(|(label, kind, detail, description)| {
+                let heading =
+                    format!("## {kind:?} {label:?} label_details=({detail:?}, {description:?})");
+                let label = ruby_lsp
+                    .label_for_completion_details(
+                        label.to_string(),
+                        kind,
+                        detail.map(str::to_string),
+                        description.map(str::to_string),
+                    )
+                    .map_or_else(|| "no label\n".to_string(), |label| render_label(&label));
+                format!("{heading}\n{label}")
+            })
+            .collect();
+
+        insta::assert_snapshot!(rendered.join("\n"));
+    }
+
+    #[test]
+    fn test_completion_highlights() {
+        use crate::language_servers::label::render_highlights;
+
+        insta::assert_snapshot!(
+            render_highlights(&RubyLsp::new()),
+            @r#"
+        CompletionKind::Text "text" -
+        CompletionKind::Method "save!" @function.method
+        CompletionKind::Function "puts" @function.method
+        CompletionKind::Constructor "new" @function.method
+        CompletionKind::Field "name" -
+        CompletionKind::Field "@name" @variable.special
+        CompletionKind::Field "@@var" @variable.special
+        CompletionKind::Field "self" @variable.special
+        CompletionKind::Field "super" @variable.special
+        CompletionKind::Variable "user" @variable
+        CompletionKind::Class "User" @type
+        CompletionKind::Interface "_Each" -
+        CompletionKind::Module "Comparable" @type
+        CompletionKind::Property "email" @property
+        CompletionKind::Unit "unit" -
+        CompletionKind::Value "value" -
+        CompletionKind::Enum "Enum" -
+        CompletionKind::Keyword "def" @keyword
+        CompletionKind::Keyword ":sym" @keyword
+        CompletionKind::Snippet "each" -
+        CompletionKind::Color "red" -
+        CompletionKind::File "user.rb" -
+        CompletionKind::Reference "run" @function.method
+        CompletionKind::Folder "app" -
+        CompletionKind::EnumMember "MEMBER" -
+        CompletionKind::Constant "VERSION" @constant
+        CompletionKind::Constant "nil" @constant.builtin
+        CompletionKind::Constant "__FILE__" @constant.builtin
+        CompletionKind::Struct "Point" -
+        CompletionKind::Event "event" -
+        CompletionKind::Operator "==" -
+        CompletionKind::TypeParameter "T" -
+        CompletionKind::Other(42) "other" -
+        "#
+        );
+    }
 }
@@ -0,0 +1,385 @@
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Map, Value};
+
+use super::project::Activation;
+
+/// The extension's own keys under `lsp.<id>.settings` for one server.
+///
+/// Every key is read on its own first, so a wrong type only drops that key
+/// and is reported as a warning instead of discarding the whole block.
+pub trait ExtensionSettings: DeserializeOwned + Default {
+    /// The JSON schema of each key, by name.
+    fn properties() -> Map<String, Value>;
+}
+
+/// Keys every server understands.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SharedSettings {
+    pub use_bundler: Option<bool>,
+    pub activation: Option<Activation>,
+    pub env_file: Option<String>,
+}
+
+impl ExtensionSettings for SharedSettings {
+    fn properties() -> Map<String, Value> {
+        properties(json!({
+            "use_bundler": {
+                "type": "boolean",
+                "description": "Start the server with `bundle exec` when its gem is in the project bundle"
+            },
+            "activation": {
+                "type": "string",
+                "enum": ["auto", "always"],
+                "default": "auto",
+                "description": "Start only when the project uses the server's tooling, or always"
+            },
+            "env_file": {
+                "type": "string",
+                "description": "A dotenv file, relative to the worktree root, whose variables are set for the server before lsp.<id>.binary.env"
+            }
+        }))
+    }
+}
+
+/// For servers without settings of their own.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoSettings {}
+
+impl ExtensionSettings for NoSettings {
+    fn properties() -> Map<String, Value> {
+        Map::new()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SorbetSettings {
+    pub enable_all_beta_lsp_features: Option<bool>,
+    pub enable_all_experimental_lsp_features: Option<bool>,
+    pub lsp_directories_missing_from_client: Vec<String>,
+}
+
+impl ExtensionSettings for SorbetSettings {
+    fn properties() -> Map<String, Value> {
+        properties(json!({
+            "enable_all_beta_lsp_features": {
+                "type": "boolean",
+                "description": "Pass --enable-all-beta-lsp-features"
+            },
+            "enable_all_experimental_lsp_features": {
+                "type": "boolean",
+                "description": "Pass --enable-all-experimental-lsp-features"
+            },
+            "lsp_directories_missing_from_client": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Directories passed with --lsp-directories-missing-from-client"
+            }
+        }))
+    }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SteepSettings {
+    pub require_root_steepfile: Option<bool>,
+    pub steepfile: Option<String>,
+}
+
+impl ExtensionSettings for SteepSettings {
+    fn properties() -> Map<String, Value> {
+        properties(json!({
+            "require_root_steepfile": {
+                "type": "boolean",
+                "default": true,
+                "description": "Refuse to start when no Steepfile is found"
+            },
+            "steepfile": {
+                "type": "string",
+                "description": "The Steepfile to start with, relative to the worktree root"
+            }
+        }))
+    }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RubocopSettings {
+    pub config_path: Option<String>,
+    pub require_config: Option<bool>,
+}
+
+impl ExtensionSettings for RubocopSettings {
+    fn properties() -> Map<String, Value> {
+        properties(json!({
+            "config_path": {
+                "type": "string",
+                "description": "The configuration file passed with --config"
+            },
+            "require_config": {
+                "type": "boolean",
+                "default": false,
+                "description": "Start only when the configuration file exists"
+            }
+        }))
+    }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HerbSettings {
+    pub package_version: Option<String>,
+}
+
+impl ExtensionSettings for HerbSettings {
+    fn properties() -> Map<String, Value> {
+        properties(json!({
+            "package_version": {
+                "type": "string",
+                "description": "Pin the npm version of @herb-tools/language-server the extension installs"
+            }
+        }))
+    }
+}
+
+fn properties(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(properties) => properties,
+        _ => unreachable!("properties are declared as an object"),
+    }
+}
+
+/// The typed `lsp.<id>.settings` of a server, and what could not be used.
+#[derive(Debug, Default)]
+pub struct Settings<S> {
+    pub shared: SharedSettings,
+    pub server: S,
+    pub warnings: Vec<String>,
+}
+
+impl<S: ExtensionSettings> Settings<S> {
+    /// Reads the extension's keys from `settings`.
+    ///
+    /// Other keys belong to the language server when it reads a configuration
+    /// section, so they are only reported when they look like a misspelled
+    /// extension key. Servers without a section report every other key.
+    pub fn load(server_id: &str, settings: Option<&Value>, forwards_other_keys: bool) -> Self {
+        let mut loaded = Self::default();
+
+        let object = match settings {
+            None | Some(Value::Null) => return loaded,
+            Some(Value::Object(object)) => object,
+            Some(_) => {
+                loaded
+                    .warnings
+                    .push(format!("lsp.{server_id}.settings must be an object"));
+                return loaded;
+            }
+        };
+
+        let shared_keys = SharedSettings::properties();
+        let server_keys = S::properties();
+        let mut shared = Map::new();
+        let mut server = Map::new();
+
+        for (key, value) in object {
+            let single = json!({ key: value });
+            let (target, checked) = if shared_keys.contains_key(key) {
+                (&mut shared, check::<SharedSettings>(single))
+            } else if server_keys.contains_key(key) {
+                (&mut server, check::<S>(single))
+            } else {
+                let suggestion = shared_keys
+                    .keys()
+                    .chain(server_keys.keys())
+                    .find(|known| normalize(known) == normalize(key));
+                match suggestion {
+                    Some(known) => loaded.warnings.push(format!(
+                        "lsp.{server_id}.settings.{key} is not a known setting, did you mean {known}?"
+                    )),
+                    None if !forwards_other_keys => loaded.warnings.push(format!(
+                        "lsp.{server_id}.settings.{key} is not a known setting"
+                    )),
+                    None => {}
+                }
+                continue;
+            };
+
+            match checked {
+                Ok(()) => {
+                    target.insert(key.clone(), value.clone());
+                }
+                Err(e) => loaded
+                    .warnings
+                    .push(format!("lsp.{server_id}.settings.{key}: {e}")),
+            }
+        }
+
+        loaded.shared = serde_json::from_value(Value::Object(shared)).unwrap_or_default();
+        loaded.server = serde_json::from_value(Value::Object(server)).unwrap_or_default();
+        loaded
+    }
+}
+
+fn check<D: DeserializeOwned>(value: Value) -> Result<(), serde_json::Error> {
+    serde_json::from_value::<D>(value).map(|_| ())
+}
+
+fn normalize(key: &str) -> String {
+    key.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The keys of `lsp.<id>.settings` that configure the extension itself.
+pub fn extension_keys<S: ExtensionSettings>() -> Vec<String> {
+    SharedSettings::properties()
+        .into_iter()
+        .chain(S::properties())
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// The JSON schema of `lsp.<id>.settings`.
+pub fn schema<S: ExtensionSettings>(server_id: &str, forwards_other_keys: bool) -> Value {
+    let mut properties = SharedSettings::properties();
+    properties.extend(S::properties());
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": format!("lsp.{server_id}.settings"),
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": forwards_other_keys
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_typed_settings() {
+        let settings = Settings::<SteepSettings>::load(
+            "steep",
+            Some(&json!({
+                "use_bundler": false,
+                "activation": "always",
+                "steepfile": "sig/Steepfile",
+                "diagnostics": "strict"
+            })),
+            true,
+        );
+
+        assert_eq!(
+            settings.shared,
+            SharedSettings {
+                use_bundler: Some(false),
+                activation: Some(Activation::Always),
+                env_file: None,
+            }
+        );
+        assert_eq!(settings.server.steepfile.as_deref(), Some("sig/Steepfile"));
+        assert_eq!(settings.server.require_root_steepfile, None);
+        assert!(settings.warnings.is_empty(), "{:?}", settings.warnings);
+    }
+
+    #[test]
+    fn test_load_reports_wrong_types() {
+        let settings = Settings::<RubocopSettings>::load(
+            "rubocop",
+            Some(&json!({
+                "use_bundler": "yes",
+                "activation": "never",
+                "require_config": true
+            })),
+            true,
+        );
+
+        assert_eq!(settings.shared, SharedSettings::default());
+        assert_eq!(settings.server.require_config, Some(true));
+        assert_eq!(settings.warnings.len(), 2);
+        assert!(
+            settings.warnings[0]
+                .starts_with("lsp.rubocop.settings.activation: unknown variant `never`"),
+            "{:?}",
+            settings.warnings
+        );
+        assert!(
+            settings.warnings[1].starts_with("lsp.rubocop.settings.use_bundler: invalid type"),
+            "{:?}",
+            settings.warnings
+        );
+    }
+
+    #[test]
+    fn test_load_reports_misspelled_keys() {
+        let settings = Settings::<SorbetSettings>::load(
+            "sorbet",
+            Some(&json!({ "useBundler": true, "enable-all-beta-lsp-features": true })),
+            true,
+        );
+
+        assert_eq!(settings.shared.use_bundler, None);
+        assert_eq!(
+            settings.warnings,
+            vec![
+                "lsp.sorbet.settings.enable-all-beta-lsp-features is not a known setting, did you mean enable_all_beta_lsp_features?",
+                "lsp.sorbet.settings.useBundler is not a known setting, did you mean use_bundler?",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_reports_unknown_keys_without_section() {
+        let settings = Settings::<NoSettings>::load(
+            "fuzzy-ruby-server",
+            Some(&json!({ "verbose": 1 })),
+            false,
+        );
+
+        assert_eq!(
+            settings.warnings,
+            vec!["lsp.fuzzy-ruby-server.settings.verbose is not a known setting"]
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_non_object() {
+        let settings = Settings::<NoSettings>::load("ruby-lsp", Some(&json!([1])), true);
+
+        assert_eq!(
+            settings.warnings,
+            vec!["lsp.ruby-lsp.settings must be an object"]
+        );
+        assert!(Settings::<NoSettings>::load("ruby-lsp", None, true)
+            .warnings
+            .is_empty());
+    }
+
+    #[test]
+    fn test_every_property_deserializes() {
+        fn assert_properties<S: ExtensionSettings>() {
+            for (key, property) in S::properties() {
+                let example = match property["type"].as_str() {
+                    Some("boolean") => json!(true),
+                    Some("string") => property
+                        .get("enum")
+                        .map_or(json!("value"), |values| values[0].clone()),
+                    Some("array") => json!(["value"]),
+                    other => panic!("{key}: unexpected type {other:?}"),
+                };
+                check::<S>(json!({ &key: example })).unwrap_or_else(|e| panic!("{key}: {e}"));
+            }
+        }
+
+        assert_properties::<SharedSettings>();
+        assert_properties::<SorbetSettings>();
+        assert_properties::<SteepSettings>();
+        assert_properties::<RubocopSettings>();
+        assert_properties::<HerbSettings>();
+    }
+}
@@ -1,6 +1,14 @@
 use zed_extension_api::{self as zed};
 
-use super::{language_server::WorktreeLike, LanguageServer};
+use super::{
+    label::{
+        default_name_highlight, method_label, plain_label, split_parameters, symbol_label,
+        typed_label, CompletionHighlights,
+    },
+    language_server::WorktreeLike,
+    settings::NoSettings,
+    LanguageServer,
+};
 
 pub struct Solargraph {}
 
@@ -8,12 +16,26 @@ impl LanguageServer for Solargraph {
     const SERVER_ID: &str = "solargraph";
     const EXECUTABLE_NAME: &str = "solargraph";
     const GEM_NAME: &str = "solargraph";
+    const CONFIGURATION_SECTION: Option<&str> = Some("solargraph");
+    type Settings = NoSettings;
 
     fn get_executable_args<T: WorktreeLike>(&self, _worktree: &T) -> Vec<String> {
         vec!["stdio".to_string()]
     }
 }
 
+/// Solargraph completes symbols such as `:name` as keywords.
+impl CompletionHighlights for Solargraph {
+    fn name_highlight(&self, kind: &zed::lsp::CompletionKind, name: &str) -> Option<&'static str> {
+        match kind {
+            zed::lsp::CompletionKind::Keyword if name.starts_with(':') => {
+                Some("string.special.symbol")
+            }
+            kind => default_name_highlight(kind, name),
+        }
+    }
+}
+
 impl Solargraph {
     pub fn new() -> Self {
         Self {}
@@ -28,104 +50,41 @@ impl Solargraph {
         } = completion;
         let kind = kind?;
 
-        let highlight_scope = match kind {
-            zed::lsp::CompletionKind::Class | zed::lsp::CompletionKind::Module => "type",
-            zed::lsp::CompletionKind::Constant if label == "nil" => "constant.builtin",
-            zed::lsp::CompletionKind::Constant
-                if label.starts_with("__") && label.ends_with("__") =>
-            {
-                "constant.builtin"
-            }
-            zed::lsp::CompletionKind::Constant => "constant",
-            zed::lsp::CompletionKind::Method | zed::lsp::CompletionKind::Function => {
-                "function.method"
-            }
-            zed::lsp::CompletionKind::Constructor => "function.method",
-            zed::lsp::CompletionKind::Keyword if label.starts_with(':') => "string.special.symbol",
-            zed::lsp::CompletionKind::Keyword => "keyword",
-            zed::lsp::CompletionKind::Field if label.starts_with("@@") => "variable.special",
-            zed::lsp::CompletionKind::Field if label.starts_with('@') => "variable.special",
-            zed::lsp::CompletionKind::Field if label == "self" || label == "super" => {
-                "variable.special"
-            }
-            zed::lsp::CompletionKind::Variable => "variable",
-            zed::lsp::CompletionKind::Property => "property",
-            _ => return None,
-        };
-
-        let label_len = label.len();
-        let name_span = zed::CodeLabelSpan::literal(label, Some(highlight_scope.to_string()));
-
-        Some(zed::CodeLabel {
-            code: Default::default(),
-            spans: if let Some(detail) = detail {
-                vec![
-                    name_span,
-                    zed::CodeLabelSpan::literal(" ", None),
-                    zed::CodeLabelSpan::literal(detail, None),
-                ]
-            } else {
-                vec![name_span]
-            },
-            filter_range: (0..label_len).into(),
-        })
+        if self.is_method(&kind) {
+            let (parameters, return_type) = split_signature(detail.as_deref().unwrap_or_default());
+            return Some(method_label(&label, parameters, return_type));
+        }
+
+        let highlight_scope = self.name_highlight(&kind, &label)?;
+        if let Some(detail) = detail.as_deref().filter(|_| self.is_value(&kind)) {
+            let type_name = detail.strip_prefix("=>").unwrap_or(detail).trim();
+            return Some(typed_label(&label, highlight_scope, type_name));
+        }
+
+        Some(plain_label(&label, highlight_scope, detail.as_deref()))
     }
 
     pub fn label_for_symbol(&self, symbol: zed::lsp::Symbol) -> Option<zed::CodeLabel> {
-        let name = &symbol.name;
-
-        match symbol.kind {
-            zed::lsp::SymbolKind::Method => {
-                let mut parts = name.split('#');
-                let container_name = parts.next()?;
-                let method_name = parts.next()?;
-
-                if parts.next().is_some() {
-                    return None;
-                }
-
-                let filter_range = 0..name.len();
-
-                let spans = vec![
-                    zed::CodeLabelSpan::literal(container_name, Some("type".to_string())),
-                    zed::CodeLabelSpan::literal("#", None),
-                    zed::CodeLabelSpan::literal(method_name, Some("function.method".to_string())),
-                ];
-
-                Some(zed::CodeLabel {
-                    code: name.to_string(),
-                    spans,
-                    filter_range: filter_range.into(),
-                })
-            }
-            zed::lsp::SymbolKind::Class | zed::lsp::SymbolKind::Module => {
-                let class = "class ";
-                let code = format!("{class}{name}");
-                let filter_range = 0..name.len();
-                let display_range = class.len()..class.len() + name.len();
-
-                Some(zed::CodeLabel {
-                    code,
-                    spans: vec![zed::CodeLabelSpan::code_range(display_range)],
-                    filter_range: filter_range.into(),
-                })
-            }
-            zed::lsp::SymbolKind::Constant => {
-                let code = name.clone();
-                let filter_range = 0..name.len();
-                let display_range = 0..name.len();
-
-                Some(zed::CodeLabel {
-                    code,
-                    spans: vec![zed::CodeLabelSpan::code_range(display_range)],
-                    filter_range: filter_range.into(),
-                })
-            }
-            _ => None,
-        }
+        symbol_label(&symbol)
     }
 }
 
+/// Splits Solargraph's method detail, `(name, opts = {}) => String`, into the
+/// parameter list and the return type. Either part may be missing.
+fn split_signature(detail: &str) -> (Option<&str>, Option<&str>) {
+    let detail = detail.trim();
+    let (parameters, rest) = match split_parameters(detail) {
+        Some((parameters, rest)) => (Some(parameters), rest.trim_start()),
+        None => (None, detail),
+    };
+    let return_type = rest
+        .strip_prefix("=>")
+        .map(str::trim)
+        .filter(|return_type| !return_type.is_empty());
+
+    (parameters, return_type)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::language_servers::{language_server::FakeWorktree, LanguageServer, Solargraph};
@@ -150,4 +109,109 @@ mod tests {
             vec!["stdio"]
         );
     }
+
+    #[test]
+    fn test_workspace_configuration() {
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.set_server_settings(
+            Solargraph::SERVER_ID.to_string(),
+            serde_json::json!({
+                "use_bundler": true,
+                "diagnostics": true,
+                "formatting": false
+            }),
+        );
+
+        assert_eq!(
+            Solargraph::workspace_configuration(Solargraph::SERVER_ID, &mock_worktree),
+            Ok(Some(serde_json::json!({
+                "solargraph": { "diagnostics": true, "formatting": false }
+            })))
+        );
+    }
+
+    #[test]
+    fn test_completion_labels() {
+        use crate::language_servers::label::{completion, render_completions};
+        use zed_extension_api::lsp::CompletionKind;
+
+        let solargraph = Solargraph::new();
+        let completions = vec![
+            completion(
+                "fetch",
+                CompletionKind::Method,
+                Some("(key, default = nil) => String"),
+            ),
+            completion(
+                "find_each",
+                CompletionKind::Method,
+                Some("(batch_size: 1000, &block) => Enumerator<User>"),
+            ),
+            completion("name", CompletionKind::Method, Some("=> String")),
+            completion("save!", CompletionKind::Method, None),
+            completion("puts", CompletionKind::Function, Some("(*args) => nil")),
+            completion("new", CompletionKind::Constructor, Some("(name) => User")),
+            completion("User", CompletionKind::Class, None),
+            completion("Comparable", CompletionKind::Module, None),
+            completion("VERSION", CompletionKind::Constant, Some("String")),
+            completion("nil", CompletionKind::Constant, None),
+            completion("__FILE__", CompletionKind::Constant, None),
+            completion("def", CompletionKind::Keyword, None),
+            completion(":name", CompletionKind::Keyword, None),
+            completion("@name", CompletionKind::Field, Some("String")),
+            completion("@@count", CompletionKind::Field, Some("Integer")),
+            completion("self", CompletionKind::Field, None),
+            completion("user", CompletionKind::Variable, Some("User")),
+            completion("email", CompletionKind::Property, Some("=> String, nil")),
+            completion("each", CompletionKind::Snippet, None),
+        ];
+
+        insta::assert_snapshot!(render_completions(completions, |completion| {
+            solargraph.label_for_completion(completion)
+        }));
+    }
+
+    #[test]
+    fn test_completion_highlights() {
+        use crate::language_servers::label::render_highlights;
+
+        insta::assert_snapshot!(
+            render_highlights(&Solargraph::new()),
+            @r#"
+        CompletionKind::Text "text" -
+        CompletionKind::Method "save!" @function.method
+        CompletionKind::Function "puts" @function.method
+        CompletionKind::Constructor "new" @function.method
+        CompletionKind::Field "name" -
+        CompletionKind::Field "@name" @variable.special
+        CompletionKind::Field "@@var" @variable.special
+        CompletionKind::Field "self" @variable.special
+        CompletionKind::Field "super" @variable.special
+        CompletionKind::Variable "user" @variable
+        CompletionKind::Class "User" @type
+        CompletionKind::Interface "_Each" -
+        CompletionKind::Module "Comparable" @type
+        CompletionKind::Property "email" @property
+        CompletionKind::Unit "unit" -
+        CompletionKind::Value "value" -
+        CompletionKind::Enum "Enum" -
+        CompletionKind::Keyword "def" @keyword
+        CompletionKind::Keyword ":sym" @string.special.symbol
+        CompletionKind::Snippet "each" -
+        CompletionKind::Color "red" -
+        CompletionKind::File "user.rb" -
+        CompletionKind::Reference "run" -
+        CompletionKind::Folder "app" -
+        CompletionKind::EnumMember "MEMBER" -
+        CompletionKind::Constant "VERSION" @constant
+        CompletionKind::Constant "nil" @constant.builtin
+        CompletionKind::Constant "__FILE__" @constant.builtin
+        CompletionKind::Struct "Point" -
+        CompletionKind::Event "event" -
+        CompletionKind::Operator "==" -
+        CompletionKind::TypeParameter "T" -
+        CompletionKind::Other(42) "other" -
+        "#
+        );
+    }
 }
@@ -1,4 +1,14 @@
-use super::{language_server::WorktreeLike, LanguageServer};
+use std::ops::Range;
+
+use zed_extension_api::{self as zed};
+
+use super::{
+    label::{plain_label, symbol_label, typed_label, CompletionHighlights},
+    language_server::WorktreeLike,
+    project::{ProjectMarkers, SORBET_CONFIG},
+    settings::SorbetSettings,
+    LanguageServer,
+};
 
 pub struct Sorbet {}
 
@@ -6,52 +16,217 @@ impl LanguageServer for Sorbet {
     const SERVER_ID: &str = "sorbet";
     const EXECUTABLE_NAME: &str = "srb";
     const GEM_NAME: &str = "sorbet";
+    type Settings = SorbetSettings;
+
+    fn inactive_reason(&self, markers: &ProjectMarkers) -> Option<String> {
+        if markers.has_file(SORBET_CONFIG) || markers.depends_on(Self::GEM_NAME) {
+            None
+        } else {
+            Some(format!(
+                "no {SORBET_CONFIG} found and {} is not in the Gemfile.lock",
+                Self::GEM_NAME
+            ))
+        }
+    }
 
     fn get_executable_args<T: WorktreeLike>(&self, worktree: &T) -> Vec<String> {
-        let binary_settings = worktree
-            .lsp_binary_settings(Self::SERVER_ID)
-            .unwrap_or_default();
+        let mut args = vec!["tc".to_string(), "--lsp".to_string()];
 
         // test if sorbet/config is present
-        match worktree.read_text_file("sorbet/config") {
-            Ok(_) => {
-                // Config file exists, prefer custom arguments if available.
-                binary_settings
-                    .and_then(|bs| bs.arguments)
-                    .unwrap_or_else(|| {
-                        vec![
-                            "tc".to_string(),
-                            "--lsp".to_string(),
-                            "--enable-experimental-lsp-document-highlight".to_string(),
-                        ]
-                    })
+        let config = match worktree.read_text_file(SORBET_CONFIG) {
+            Ok(content) => {
+                args.push("--enable-experimental-lsp-document-highlight".to_string());
+                SorbetConfig::parse(&content)
             }
             Err(_) => {
-                // gross, but avoid sorbet errors in a non-sorbet
+                // Reached with `"activation": "always"` or when sorbet is only
+                // in the Gemfile.lock. Avoid sorbet errors in a non-sorbet
                 // environment by using an empty config
-                vec![
-                    "tc".to_string(),
-                    "--lsp".to_string(),
-                    "--dir".to_string(),
-                    "./".to_string(),
-                ]
+                args.extend(["--dir".to_string(), "./".to_string()]);
+                SorbetConfig::default()
+            }
+        };
+
+        // Without Watchman on PATH Sorbet exits right after starting.
+        if worktree.which("watchman").is_none() && !config.contains("--disable-watchman") {
+            args.push("--disable-watchman".to_string());
+        }
+
+        let settings = Self::settings(Self::SERVER_ID, worktree)
+            .map(|settings| settings.server)
+            .unwrap_or_default();
+        for (enabled, option) in [
+            (
+                settings.enable_all_beta_lsp_features,
+                "--enable-all-beta-lsp-features",
+            ),
+            (
+                settings.enable_all_experimental_lsp_features,
+                "--enable-all-experimental-lsp-features",
+            ),
+        ] {
+            if enabled == Some(true) && !config.contains(option) {
+                args.push(option.to_string());
             }
         }
+        for directory in settings.lsp_directories_missing_from_client {
+            args.push(format!("--lsp-directories-missing-from-client={directory}"));
+        }
+
+        let user_args = worktree
+            .lsp_binary_settings(Self::SERVER_ID)
+            .unwrap_or_default()
+            .and_then(|bs| bs.arguments)
+            .unwrap_or_default();
+        merge_arguments(args, user_args)
+    }
+
+    /// [`Self::get_executable_args`] already appends `binary.arguments`, so a
+    /// custom Sorbet binary keeps the options derived from the project.
+    fn configured_binary_args<T: WorktreeLike>(
+        &self,
+        worktree: &T,
+        _arguments: Option<Vec<String>>,
+    ) -> Option<Vec<String>> {
+        Some(self.get_executable_args(worktree))
+    }
+}
+
+/// The options listed in `sorbet/config`, one per line.
+#[derive(Debug, Default)]
+struct SorbetConfig {
+    options: Vec<String>,
+}
+
+impl SorbetConfig {
+    fn parse(content: &str) -> Self {
+        let options = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+
+        Self { options }
+    }
+
+    fn contains(&self, option: &str) -> bool {
+        self.options.iter().any(|line| {
+            line == option
+                || line
+                    .strip_prefix(option)
+                    .is_some_and(|rest| rest.starts_with('='))
+        })
+    }
+}
+
+/// Appends the user's `binary.arguments` to the generated ones. The `tc --lsp`
+/// prefix and flags already present are dropped so that settings written for
+/// the old replace-everything behavior keep working.
+fn merge_arguments(mut args: Vec<String>, user_args: Vec<String>) -> Vec<String> {
+    let mut user_args = user_args.into_iter().peekable();
+    if user_args.peek().is_some_and(|arg| arg == "tc") {
+        user_args.next();
+    }
+
+    for arg in user_args {
+        if arg.starts_with("--") && arg != "--dir" && args.contains(&arg) {
+            continue;
+        }
+        args.push(arg);
     }
+
+    args
 }
 
+impl CompletionHighlights for Sorbet {}
+
 impl Sorbet {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Builds the label from the completion's `detail`, where Sorbet reports a
+    /// method's `sig`, such as `sig { params(x: Integer).returns(String) }`, and
+    /// the type of a variable or constant.
+    pub fn label_for_completion(&self, completion: zed::lsp::Completion) -> Option<zed::CodeLabel> {
+        let kind = completion.kind?;
+        let name = completion.label;
+        let detail = completion
+            .detail
+            .map(|detail| {
+                // Multi-line sigs continue with `.returns(...)` on their own line.
+                detail
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .replace(" .", ".")
+            })
+            .filter(|detail| !detail.is_empty());
+
+        if self.is_method(&kind) {
+            if let Some(body) = detail.as_deref().and_then(sig_body) {
+                return Some(zed::CodeLabel {
+                    spans: vec![
+                        zed::CodeLabelSpan::literal(&name, Some("function.method".to_string())),
+                        zed::CodeLabelSpan::literal(" ", None),
+                        zed::CodeLabelSpan::code_range(body),
+                    ],
+                    code: detail?,
+                    filter_range: (0..name.len()).into(),
+                });
+            }
+        }
+
+        let highlight = self.name_highlight(&kind, &name)?;
+        match detail {
+            Some(type_name) if self.is_value(&kind) => {
+                Some(typed_label(&name, highlight, &type_name))
+            }
+            detail => Some(plain_label(&name, highlight, detail.as_deref())),
+        }
+    }
+
+    pub fn label_for_symbol(&self, symbol: zed::lsp::Symbol) -> Option<zed::CodeLabel> {
+        symbol_label(&symbol)
+    }
+}
+
+/// The range of the block in a `sig { ... }` or `sig do ... end`, which is
+/// what the label shows of a signature.
+fn sig_body(sig: &str) -> Option<Range<usize>> {
+    if !sig.starts_with("sig") {
+        return None;
+    }
+
+    let body = if sig.ends_with('}') {
+        sig.find('{')? + 1..sig.len() - 1
+    } else if sig.ends_with(" end") {
+        sig.find(" do ")? + 4..sig.len() - 4
+    } else {
+        return None;
+    };
+
+    let inner = &sig[body.clone()];
+    let start = body.start + inner.len() - inner.trim_start().len();
+    let end = body.end - (inner.len() - inner.trim_end().len());
+    (start < end).then_some(start..end)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::language_servers::{
-        language_server::{FakeWorktree, LspBinarySettings},
-        LanguageServer, Sorbet,
-    };
+    use super::*;
+    use crate::language_servers::language_server::{FakeHost, FakeWorktree, LspBinarySettings};
+    use zed_extension_api::serde_json::json;
+
+    fn worktree_with_watchman() -> FakeWorktree {
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.set_which(
+            "watchman".to_string(),
+            Some("/usr/local/bin/watchman".to_string()),
+        );
+        fake_worktree
+    }
 
     #[test]
     fn test_server_id() {
@@ -71,7 +246,7 @@ mod tests {
     #[test]
     fn test_executable_args_no_config_file() {
         let sorbet = Sorbet::new();
-        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        let mut fake_worktree = worktree_with_watchman();
 
         fake_worktree.add_file(
             "sorbet/config".to_string(),
@@ -95,30 +270,36 @@ mod tests {
     #[test]
     fn test_executable_args_with_config_and_custom_settings() {
         let sorbet = Sorbet::new();
-        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        let mut fake_worktree = worktree_with_watchman();
 
         fake_worktree.add_file("sorbet/config".to_string(), Ok("--dir\n.".to_string()));
 
-        let custom_args = vec!["--custom-arg1".to_string(), "value1".to_string()];
         fake_worktree.add_lsp_binary_setting(
             Sorbet::SERVER_ID.to_string(),
             Ok(Some(LspBinarySettings {
                 path: None,
-                arguments: Some(custom_args.clone()),
+                arguments: Some(vec!["--custom-arg1".to_string(), "value1".to_string()]),
+                env: None,
             })),
         );
 
         assert_eq!(
             sorbet.get_executable_args(&fake_worktree),
-            custom_args,
-            "Should use custom arguments when config and settings are present"
+            vec![
+                "tc",
+                "--lsp",
+                "--enable-experimental-lsp-document-highlight",
+                "--custom-arg1",
+                "value1"
+            ],
+            "Should append custom arguments to the generated ones"
         );
     }
 
     #[test]
     fn test_executable_args_with_config_no_custom_settings() {
         let sorbet = Sorbet::new();
-        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        let mut fake_worktree = worktree_with_watchman();
 
         fake_worktree.add_file("sorbet/config".to_string(), Ok("--dir\n.".to_string()));
         fake_worktree.add_lsp_binary_setting(Sorbet::SERVER_ID.to_string(), Ok(None));
@@ -138,7 +319,7 @@ mod tests {
     #[test]
     fn test_executable_args_with_config_lsp_settings_is_empty_struct() {
         let sorbet = Sorbet::new();
-        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        let mut fake_worktree = worktree_with_watchman();
 
         fake_worktree.add_file("sorbet/config".to_string(), Ok("--dir\n.".to_string()));
         fake_worktree.add_lsp_binary_setting(
@@ -157,4 +338,207 @@ mod tests {
                 "Should use default arguments when config is present and LSP settings have no arguments"
             );
     }
+
+    #[test]
+    fn test_executable_args_disable_watchman_when_missing() {
+        let sorbet = Sorbet::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file("sorbet/config".to_string(), Ok("--dir\n.".to_string()));
+
+        assert_eq!(
+            sorbet.get_executable_args(&fake_worktree),
+            vec![
+                "tc",
+                "--lsp",
+                "--enable-experimental-lsp-document-highlight",
+                "--disable-watchman"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_executable_args_respect_sorbet_config() {
+        let sorbet = Sorbet::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file(
+            "sorbet/config".to_string(),
+            Ok(
+                "--dir\n.\n# editors\n--disable-watchman\n--enable-all-beta-lsp-features\n"
+                    .to_string(),
+            ),
+        );
+        fake_worktree.set_server_settings(
+            Sorbet::SERVER_ID.to_string(),
+            json!({ "enable_all_beta_lsp_features": true }),
+        );
+
+        assert_eq!(
+            sorbet.get_executable_args(&fake_worktree),
+            vec![
+                "tc",
+                "--lsp",
+                "--enable-experimental-lsp-document-highlight"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_executable_args_lsp_feature_settings() {
+        let sorbet = Sorbet::new();
+        let mut fake_worktree = worktree_with_watchman();
+        fake_worktree.add_file("sorbet/config".to_string(), Ok("--dir\n.".to_string()));
+        fake_worktree.set_server_settings(
+            Sorbet::SERVER_ID.to_string(),
+            json!({
+                "enable_all_beta_lsp_features": true,
+                "enable_all_experimental_lsp_features": false,
+                "lsp_directories_missing_from_client": ["bazel-out", "vendor/cache"]
+            }),
+        );
+
+        assert_eq!(
+            sorbet.get_executable_args(&fake_worktree),
+            vec![
+                "tc",
+                "--lsp",
+                "--enable-experimental-lsp-document-highlight",
+                "--enable-all-beta-lsp-features",
+                "--lsp-directories-missing-from-client=bazel-out",
+                "--lsp-directories-missing-from-client=vendor/cache"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_executable_args_merge_legacy_full_arguments() {
+        let sorbet = Sorbet::new();
+        let mut fake_worktree = worktree_with_watchman();
+        fake_worktree.add_file("sorbet/config".to_string(), Ok("--dir\n.".to_string()));
+        fake_worktree.add_lsp_binary_setting(
+            Sorbet::SERVER_ID.to_string(),
+            Ok(Some(LspBinarySettings {
+                path: None,
+                arguments: Some(
+                    [
+                        "tc",
+                        "--lsp",
+                        "--enable-experimental-lsp-document-highlight",
+                        "--dir",
+                        "app",
+                    ]
+                    .map(String::from)
+                    .to_vec(),
+                ),
+                env: None,
+            })),
+        );
+
+        assert_eq!(
+            sorbet.get_executable_args(&fake_worktree),
+            vec![
+                "tc",
+                "--lsp",
+                "--enable-experimental-lsp-document-highlight",
+                "--dir",
+                "app"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_configured_binary_path_keeps_derived_arguments() {
+        let sorbet = Sorbet::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_lsp_binary_setting(
+            Sorbet::SERVER_ID.to_string(),
+            Ok(Some(LspBinarySettings {
+                path: Some("/opt/sorbet/bin/srb".to_string()),
+                arguments: Some(vec!["--max-threads=4".to_string()]),
+                env: None,
+            })),
+        );
+
+        let binary = sorbet
+            .resolve_language_server_binary(
+                Sorbet::SERVER_ID,
+                &fake_worktree,
+                &FakeHost::command_free(),
+            )
+            .expect("configured path should be used");
+
+        assert_eq!(binary.path, "/opt/sorbet/bin/srb");
+        assert_eq!(
+            binary.args,
+            Some(
+                [
+                    "tc",
+                    "--lsp",
+                    "--dir",
+                    "./",
+                    "--disable-watchman",
+                    "--max-threads=4"
+                ]
+                .map(String::from)
+                .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_sorbet_config_contains() {
+        let config = SorbetConfig::parse(
+            "--dir=.\n  --disable-watchman  \n# --enable-all-beta-lsp-features\n",
+        );
+
+        assert!(config.contains("--dir"));
+        assert!(config.contains("--disable-watchman"));
+        assert!(!config.contains("--disable"));
+        assert!(!config.contains("--enable-all-beta-lsp-features"));
+    }
+
+    #[test]
+    fn test_sig_body() {
+        let sig = "sig { params(x: Integer).returns(String) }";
+        assert_eq!(
+            sig_body(sig).map(|range| &sig[range]),
+            Some("params(x: Integer).returns(String)")
+        );
+        let sig = "sig(:final) do void end";
+        assert_eq!(sig_body(sig).map(|range| &sig[range]), Some("void"));
+        assert_eq!(sig_body("sig {}"), None);
+        assert_eq!(sig_body("String"), None);
+    }
+
+    #[test]
+    fn test_completion_labels() {
+        use crate::language_servers::label::{completion, render_completions};
+        use zed_extension_api::lsp::CompletionKind;
+
+        let sorbet = Sorbet::new();
+        let completions = vec![
+            completion(
+                "fetch",
+                CompletionKind::Method,
+                Some("sig { params(key: Symbol, default: T.untyped).returns(T.nilable(String)) }"),
+            ),
+            completion(
+                "each",
+                CompletionKind::Method,
+                Some("sig do\n  params(blk: T.proc.params(arg0: Elem).void)\n    .returns(T::Array[Elem])\nend"),
+            ),
+            completion("save!", CompletionKind::Method, Some("sig { void }")),
+            completion("to_s", CompletionKind::Method, None),
+            completion("new", CompletionKind::Constructor, Some("sig { params(name: String).void }")),
+            completion("User", CompletionKind::Class, Some("T.class_of(User)")),
+            completion("VERSION", CompletionKind::Constant, Some("String")),
+            completion("@name", CompletionKind::Field, Some("T.nilable(String)")),
+            completion("user", CompletionKind::Variable, Some("User")),
+            completion("if", CompletionKind::Keyword, Some("(sorbet) keyword")),
+            completion("each", CompletionKind::Snippet, None),
+        ];
+
+        insta::assert_snapshot!(render_completions(completions, |completion| {
+            sorbet.label_for_completion(completion)
+        }));
+    }
 }
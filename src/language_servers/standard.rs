@@ -0,0 +1,122 @@
+use super::{
+    language_server::WorktreeLike,
+    project::{ProjectMarkers, STANDARD_CONFIG},
+    settings::NoSettings,
+    LanguageServer,
+};
+
+pub struct Standard {}
+
+impl LanguageServer for Standard {
+    const SERVER_ID: &str = "standardrb";
+    const EXECUTABLE_NAME: &str = "standardrb";
+    const GEM_NAME: &str = "standard";
+    type Settings = NoSettings;
+
+    fn inactive_reason(&self, markers: &ProjectMarkers) -> Option<String> {
+        if markers.has_file(STANDARD_CONFIG) || markers.depends_on(Self::GEM_NAME) {
+            None
+        } else {
+            Some(format!(
+                "no {STANDARD_CONFIG} found and {} is not in the Gemfile.lock",
+                Self::GEM_NAME
+            ))
+        }
+    }
+
+    fn get_executable_args<T: WorktreeLike>(&self, _worktree: &T) -> Vec<String> {
+        vec!["--lsp".to_string()]
+    }
+}
+
+impl Standard {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_executor::ScriptedCommandExecutor;
+    use crate::language_servers::{
+        language_server::{FakeHost, FakeWorktree},
+        LanguageServer, Standard,
+    };
+
+    #[test]
+    fn test_server_id() {
+        assert_eq!(Standard::SERVER_ID, "standardrb");
+    }
+
+    #[test]
+    fn test_executable_name() {
+        assert_eq!(Standard::EXECUTABLE_NAME, "standardrb");
+    }
+
+    #[test]
+    fn test_gem_name() {
+        assert_eq!(Standard::GEM_NAME, "standard");
+    }
+
+    #[test]
+    fn test_default_use_bundler() {
+        assert!(Standard::default_use_bundler());
+    }
+
+    #[test]
+    fn test_executable_args() {
+        let standard = Standard::new();
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        assert_eq!(standard.get_executable_args(&mock_worktree), vec!["--lsp"]);
+    }
+
+    #[test]
+    fn test_bundled_binary() {
+        let standard = Standard::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.set_which("bundle".to_string(), Some("/bin/bundle".to_string()));
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("bundle", &["info", "--version", "standard"], "1.33.0");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = standard
+            .resolve_language_server_binary(Standard::SERVER_ID, &fake_worktree, &host)
+            .expect("Standard should run through the project bundle");
+
+        assert_eq!(binary.path, "/bin/bundle");
+        assert_eq!(
+            binary.args,
+            Some(vec![
+                "exec".to_string(),
+                "standardrb".to_string(),
+                "--lsp".to_string()
+            ])
+        );
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_extension_gemset_binary() {
+        let standard = Standard::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.set_use_bundler(Standard::SERVER_ID.to_string(), false);
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("ruby", &["--version"], "ruby 3.3.0");
+        executor.expect_success(
+            "gem",
+            &["list", "--norc", "--exact", "standard"],
+            "standard (1.33.0)",
+        );
+        executor.expect_success("gem", &["outdated", "--norc"], "");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = standard
+            .resolve_language_server_binary(Standard::SERVER_ID, &fake_worktree, &host)
+            .expect("Standard should resolve from the extension gemset");
+
+        assert!(binary.path.ends_with("/bin/standardrb"));
+        assert_eq!(binary.args, Some(vec!["--lsp".to_string()]));
+        executor.assert_finished();
+    }
+}
@@ -1,15 +1,50 @@
-use super::{language_server::WorktreeLike, LanguageServer};
+use std::{path::Path, sync::LazyLock};
+
+use regex::Regex;
+
+use super::{
+    label::{plain_label, rbs_method_label, rbs_spans, symbol_label, CompletionHighlights},
+    language_server::{ExtensionHost, WorktreeLike, ZedHost},
+    project::{ProjectMarkers, STEEPFILE},
+    settings::SteepSettings,
+    LanguageServer,
+};
 use zed_extension_api::{self as zed};
 
-pub struct Steep {}
+const RBS_COLLECTION_CONFIG: &str = "rbs_collection.yaml";
+const RBS_COLLECTION_LOCKFILE: &str = "rbs_collection.lock.yaml";
+
+pub struct Steep {
+    /// The Steepfile to pass with `--steepfile`, when it is not the root one.
+    steepfile: Option<String>,
+    /// What the last start found, for the `/ruby-doctor` report.
+    notes: Vec<String>,
+}
 
 impl LanguageServer for Steep {
     const SERVER_ID: &str = "steep";
     const EXECUTABLE_NAME: &str = "steep";
     const GEM_NAME: &str = "steep";
+    const CONFIGURATION_SECTION: Option<&str> = Some("steep");
+    type Settings = SteepSettings;
 
     fn get_executable_args<T: WorktreeLike>(&self, _worktree: &T) -> Vec<String> {
-        vec!["langserver".to_string()]
+        let mut args = vec!["langserver".to_string()];
+        if let Some(steepfile) = &self.steepfile {
+            args.push(format!("--steepfile={steepfile}"));
+        }
+        args
+    }
+
+    fn inactive_reason(&self, markers: &ProjectMarkers) -> Option<String> {
+        if markers.has_file(STEEPFILE) || markers.depends_on(Self::GEM_NAME) {
+            None
+        } else {
+            Some(format!(
+                "no {STEEPFILE} found and {} is not in the Gemfile.lock",
+                Self::GEM_NAME
+            ))
+        }
     }
 
     fn language_server_command(
@@ -17,43 +52,317 @@ impl LanguageServer for Steep {
         language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
     ) -> zed::Result<zed::Command> {
-        let lsp_settings =
-            zed::settings::LspSettings::for_worktree(language_server_id.as_ref(), worktree)?;
-
-        let require_root_steepfile = lsp_settings
-            .settings
-            .as_ref()
-            .and_then(|settings| settings["require_root_steepfile"].as_bool())
-            .unwrap_or(true);
+        let host = ZedHost::new(language_server_id);
+        self.prepare(language_server_id.as_ref(), worktree, &host)?;
 
-        if require_root_steepfile && worktree.read_text_file("Steepfile").is_err() {
-            return Err("Steep language server requires a Steepfile in the project root. You can disable this requirement by setting 'require_root_steepfile': false in your LSP settings.".to_string());
-        }
-
-        let binary = self.language_server_binary(language_server_id, worktree)?;
+        let binary = self.language_server_binary(language_server_id, worktree, &host)?;
+        let env = Self::server_env(
+            language_server_id.as_ref(),
+            worktree,
+            &host,
+            binary.env.unwrap_or_default(),
+        )?;
 
-        zed::set_language_server_installation_status(
-            language_server_id,
-            &zed::LanguageServerInstallationStatus::None,
-        );
+        self.finish_installation(language_server_id.as_ref(), worktree, &host);
 
         Ok(zed::Command {
             command: binary.path,
             args: binary.args.unwrap_or(self.get_executable_args(worktree)),
-            env: binary.env.unwrap_or_default(),
+            env,
         })
     }
 }
 
+impl CompletionHighlights for Steep {}
+
 impl Steep {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            steepfile: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn label_for_completion(&self, completion: zed::lsp::Completion) -> Option<zed::CodeLabel> {
+        let (detail, description) = match completion.label_details {
+            Some(label_details) => (
+                label_details.detail.or(completion.detail),
+                label_details.description,
+            ),
+            None => (completion.detail, None),
+        };
+
+        self.label_for_completion_details(completion.label, completion.kind?, detail, description)
+    }
+
+    /// Builds the label from the details Steep reports in RBS: a method's type,
+    /// such as `(::Integer index) -> ::String`, and its owner, such as
+    /// `::Array#fetch`, or the type of a variable.
+    fn label_for_completion_details(
+        &self,
+        label: String,
+        kind: zed::lsp::CompletionKind,
+        detail: Option<String>,
+        description: Option<String>,
+    ) -> Option<zed::CodeLabel> {
+        let details: Vec<String> = [detail, description]
+            .into_iter()
+            .flatten()
+            .filter(|detail| !detail.trim().is_empty())
+            .collect();
+
+        // Keyword arguments are described in prose rather than RBS.
+        if label.ends_with(':') {
+            let details = (!details.is_empty()).then(|| details.join(" "));
+            return Some(plain_label(
+                &label,
+                "variable.parameter",
+                details.as_deref(),
+            ));
+        }
+
+        let (method_type, details) = match details.iter().position(|detail| is_method_type(detail))
+        {
+            Some(index) if self.is_method(&kind) => {
+                let mut details = details;
+                let method_type = details.remove(index);
+                (Some(method_type), details)
+            }
+            _ => (None, details),
+        };
+
+        let mut code_label = match method_type {
+            Some(method_type) => rbs_method_label(&label, &method_type),
+            None => plain_label(&label, self.name_highlight(&kind, &label)?, None),
+        };
+        for detail in details {
+            code_label
+                .spans
+                .push(zed::CodeLabelSpan::literal(" ", None));
+            code_label.spans.extend(rbs_spans(&detail));
+        }
+
+        Some(code_label)
+    }
+
+    pub fn label_for_symbol(&self, symbol: zed::lsp::Symbol) -> Option<zed::CodeLabel> {
+        symbol_label(&symbol)
+    }
+
+    /// Picks the Steepfile to start with and notes the targets it covers, and
+    /// an RBS collection that still needs to be installed.
+    ///
+    /// A project with a Steepfile anywhere in the worktree starts Steep, even
+    /// without one at the root.
+    fn prepare<T: WorktreeLike, H: ExtensionHost>(
+        &mut self,
+        server_id: &str,
+        worktree: &T,
+        host: &H,
+    ) -> zed::Result<()> {
+        let settings = Self::settings(server_id, worktree)?.server;
+
+        let Some(path) = settings
+            .steepfile
+            .or_else(|| Self::discover_steepfile(worktree, host))
+        else {
+            self.ensure_active(server_id, worktree, host)?;
+
+            if settings.require_root_steepfile.unwrap_or(true) {
+                return Err("Steep language server requires a Steepfile in the project. You can disable this requirement by setting 'require_root_steepfile': false in your LSP settings.".to_string());
+            }
+
+            self.steepfile = None;
+            self.notes = vec!["Started without a Steepfile".to_string()];
+            return Ok(());
+        };
+
+        let content = worktree
+            .read_text_file(&path)
+            .map_err(|e| format!("Unable to read {path}: {e}"))?;
+        let steepfile = Steepfile::parse(&content);
+        let directory = Path::new(&path).parent().unwrap_or(Path::new(""));
+
+        let mut notes = vec![format!("Using {path}{}", steepfile.describe(directory))];
+        notes.extend(rbs_collection_warning(worktree, directory));
+        for note in &notes {
+            eprintln!("{server_id}: {note}");
+        }
+
+        self.steepfile = (path != STEEPFILE).then_some(path);
+        self.notes = notes;
+        Ok(())
+    }
+
+    /// The Steepfile and targets of the last start, and any warnings.
+    pub fn doctor_report(&self) -> Vec<String> {
+        self.notes.clone()
+    }
+
+    /// Finds the root Steepfile, or else the shallowest nested one.
+    ///
+    /// Zed cannot list worktree files, so nested Steepfiles are found through
+    /// `git ls-files` and only when a command executor is available.
+    fn discover_steepfile<T: WorktreeLike, H: ExtensionHost>(
+        worktree: &T,
+        host: &H,
+    ) -> Option<String> {
+        if worktree.read_text_file(STEEPFILE).is_ok() {
+            return Some(STEEPFILE.to_string());
+        }
+
+        let executor = host.command_executor()?;
+
+        let root_path = worktree.root_path();
+        let shell_env = worktree.shell_env();
+        let env_vars: Vec<(&str, &str)> = shell_env
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let output = executor.execute(
+            "git",
+            &[
+                "-C",
+                &root_path,
+                "ls-files",
+                "--cached",
+                "--others",
+                "--exclude-standard",
+                "--",
+                ":(glob)**/Steepfile",
+            ],
+            &env_vars,
+        );
+
+        let output = output.ok().filter(|output| output.status == Some(0))?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|path| !path.is_empty() && *path != STEEPFILE)
+            .min_by_key(|path| (path.matches('/').count(), *path))
+            .map(str::to_string)
+    }
+}
+
+/// Whether an RBS detail is a method type, such as `[T] (T) -> T`, rather
+/// than a type or a method name.
+fn is_method_type(rbs: &str) -> bool {
+    let rbs = rbs.trim_start();
+    rbs.starts_with(['(', '[', '{', '^']) || rbs.starts_with("->")
+}
+
+/// Warns about an RBS collection next to a Steepfile that has not been
+/// installed, which leaves Steep without the gems' signatures.
+fn rbs_collection_warning<T: WorktreeLike>(worktree: &T, directory: &Path) -> Option<String> {
+    let config = directory.join(RBS_COLLECTION_CONFIG);
+    let lockfile = directory.join(RBS_COLLECTION_LOCKFILE);
+
+    (worktree.read_text_file(&config.to_string_lossy()).is_ok()
+        && worktree.read_text_file(&lockfile.to_string_lossy()).is_err())
+    .then(|| {
+        format!(
+            "{} exists but {} is missing. Run `rbs collection install` so Steep can load the collection's signatures.",
+            config.display(),
+            lockfile.display(),
+        )
+    })
+}
+
+/// The targets declared in a Steepfile.
+#[derive(Debug, Default, PartialEq)]
+struct Steepfile {
+    targets: Vec<SteepTarget>,
+}
+
+#[derive(Debug, PartialEq)]
+struct SteepTarget {
+    name: String,
+    check: Vec<String>,
+}
+
+impl Steepfile {
+    fn parse(content: &str) -> Self {
+        static TARGET_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r#"^\s*target[\s(]+:?["']?([\w-]+)["']?"#).unwrap());
+        static CHECK_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^\s*check[\s(]").unwrap());
+        static STRING_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r#"["']([^"']+)["']"#).unwrap());
+
+        let mut steepfile = Self::default();
+
+        for line in content.lines() {
+            if let Some(captures) = TARGET_REGEX.captures(line) {
+                steepfile.targets.push(SteepTarget {
+                    name: captures[1].to_string(),
+                    check: Vec::new(),
+                });
+            } else if CHECK_REGEX.is_match(line) {
+                if let Some(target) = steepfile.targets.last_mut() {
+                    target.check.extend(
+                        STRING_REGEX
+                            .captures_iter(line)
+                            .map(|captures| captures[1].to_string()),
+                    );
+                }
+            }
+        }
+
+        steepfile
+    }
+
+    /// Summarizes which paths, relative to the worktree root, each target checks.
+    fn describe(&self, directory: &Path) -> String {
+        self.targets
+            .iter()
+            .map(|target| {
+                let paths: Vec<String> = target
+                    .check
+                    .iter()
+                    .map(|path| directory.join(path).display().to_string())
+                    .collect();
+                if paths.is_empty() {
+                    format!("; target {} checks nothing", target.name)
+                } else {
+                    format!("; target {} checks {}", target.name, paths.join(", "))
+                }
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::language_servers::{language_server::FakeWorktree, LanguageServer, Steep};
+    use super::*;
+    use crate::command_executor::ScriptedCommandExecutor;
+    use crate::language_servers::language_server::{FakeHost, FakeWorktree};
+    use zed_extension_api::serde_json::json;
+
+    const GIT_LS_FILES: &[&str] = &[
+        "-C",
+        "/path/to/project",
+        "ls-files",
+        "--cached",
+        "--others",
+        "--exclude-standard",
+        "--",
+        ":(glob)**/Steepfile",
+    ];
+
+    const STEEPFILE_CONTENT: &str = r#"
+D = Steep::Diagnostic
+
+target :app do
+  signature "sig"
+  check "app", "lib"
+  ignore "lib/templates/*.rb"
+  configure_code_diagnostics(D::Ruby.strict)
+end
+
+target(:test) do
+  check("test")
+end
+"#;
 
     #[test]
     fn test_server_id() {
@@ -65,6 +374,20 @@ mod tests {
         assert_eq!(Steep::EXECUTABLE_NAME, "steep");
     }
 
+    #[test]
+    fn test_workspace_configuration() {
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.set_server_settings(
+            Steep::SERVER_ID.to_string(),
+            json!({ "steepfile": "sig/Steepfile", "logLevel": "debug" }),
+        );
+
+        assert_eq!(
+            Steep::workspace_configuration(Steep::SERVER_ID, &fake_worktree),
+            Ok(Some(json!({ "steep": { "logLevel": "debug" } })))
+        );
+    }
+
     #[test]
     fn test_default_use_bundler() {
         assert!(Steep::default_use_bundler());
@@ -80,4 +403,255 @@ mod tests {
             vec!["langserver"]
         );
     }
+
+    #[test]
+    fn test_executable_args_with_nested_steepfile() {
+        let mut steep = Steep::new();
+        steep.steepfile = Some("services/api/Steepfile".to_string());
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        assert_eq!(
+            steep.get_executable_args(&mock_worktree),
+            vec!["langserver", "--steepfile=services/api/Steepfile"]
+        );
+    }
+
+    #[test]
+    fn test_parse_steepfile() {
+        let steepfile = Steepfile::parse(STEEPFILE_CONTENT);
+
+        assert_eq!(
+            steepfile,
+            Steepfile {
+                targets: vec![
+                    SteepTarget {
+                        name: "app".to_string(),
+                        check: vec!["app".to_string(), "lib".to_string()],
+                    },
+                    SteepTarget {
+                        name: "test".to_string(),
+                        check: vec!["test".to_string()],
+                    },
+                ],
+            }
+        );
+        assert_eq!(
+            steepfile.describe(Path::new("services/api")),
+            "; target app checks services/api/app, services/api/lib; target test checks services/api/test"
+        );
+    }
+
+    #[test]
+    fn test_prepare_uses_root_steepfile() {
+        let mut steep = Steep::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file("Steepfile".to_string(), Ok(STEEPFILE_CONTENT.to_string()));
+        // No commands are expected: with a root Steepfile, git is not asked
+        // for nested ones.
+        let host = FakeHost::with_executor(ScriptedCommandExecutor::new());
+
+        steep
+            .prepare(Steep::SERVER_ID, &fake_worktree, &host)
+            .expect("root Steepfile should start Steep");
+
+        assert_eq!(steep.steepfile, None);
+    }
+
+    #[test]
+    fn test_prepare_discovers_nested_steepfile() {
+        let mut steep = Steep::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file(
+            "services/api/Steepfile".to_string(),
+            Ok(STEEPFILE_CONTENT.to_string()),
+        );
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success(
+            "git",
+            GIT_LS_FILES,
+            "services/web/admin/Steepfile\nservices/api/Steepfile\n",
+        );
+
+        steep
+            .prepare(
+                Steep::SERVER_ID,
+                &fake_worktree,
+                &FakeHost::with_executor(executor.clone()),
+            )
+            .expect("nested Steepfile should start Steep");
+
+        assert_eq!(steep.steepfile.as_deref(), Some("services/api/Steepfile"));
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_prepare_prefers_configured_steepfile() {
+        let mut steep = Steep::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file("Steepfile".to_string(), Ok(String::new()));
+        fake_worktree.add_file("sig/Steepfile".to_string(), Ok(String::new()));
+        fake_worktree.set_server_settings(
+            Steep::SERVER_ID.to_string(),
+            json!({ "steepfile": "sig/Steepfile" }),
+        );
+
+        steep
+            .prepare(Steep::SERVER_ID, &fake_worktree, &FakeHost::command_free())
+            .expect("configured Steepfile should start Steep");
+
+        assert_eq!(steep.steepfile.as_deref(), Some("sig/Steepfile"));
+    }
+
+    #[test]
+    fn test_prepare_without_steepfile() {
+        let mut steep = Steep::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file(
+            "Gemfile.lock".to_string(),
+            Ok("DEPENDENCIES\n  steep\n".to_string()),
+        );
+
+        let error = steep
+            .prepare(Steep::SERVER_ID, &fake_worktree, &FakeHost::command_free())
+            .expect_err("Steep should require a Steepfile by default");
+        assert!(error.contains("requires a Steepfile"), "{error}");
+
+        fake_worktree.set_server_settings(
+            Steep::SERVER_ID.to_string(),
+            json!({ "require_root_steepfile": false }),
+        );
+        steep
+            .prepare(Steep::SERVER_ID, &fake_worktree, &FakeHost::command_free())
+            .expect("Steep should start without a Steepfile when allowed");
+        assert_eq!(steep.steepfile, None);
+    }
+
+    #[test]
+    fn test_prepare_warns_about_missing_rbs_collection() {
+        let mut steep = Steep::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file("Steepfile".to_string(), Ok(STEEPFILE_CONTENT.to_string()));
+        fake_worktree.add_file("rbs_collection.yaml".to_string(), Ok(String::new()));
+        let host = FakeHost::command_free();
+
+        steep
+            .prepare(Steep::SERVER_ID, &fake_worktree, &host)
+            .expect("Steep should start without the collection");
+
+        assert!(host.statuses().is_empty());
+        assert_eq!(
+            steep.doctor_report(),
+            vec![
+                "Using Steepfile; target app checks app, lib; target test checks test",
+                "rbs_collection.yaml exists but rbs_collection.lock.yaml is missing. Run `rbs collection install` so Steep can load the collection's signatures.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rbs_collection_warning() {
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file(
+            "services/api/rbs_collection.yaml".to_string(),
+            Ok(String::new()),
+        );
+
+        let message = rbs_collection_warning(&fake_worktree, Path::new("services/api"))
+            .expect("missing lockfile should warn");
+        assert!(
+            message.contains("services/api/rbs_collection.lock.yaml"),
+            "{message}"
+        );
+
+        fake_worktree.add_file(
+            "services/api/rbs_collection.lock.yaml".to_string(),
+            Ok(String::new()),
+        );
+        assert_eq!(
+            rbs_collection_warning(&fake_worktree, Path::new("services/api")),
+            None
+        );
+        assert_eq!(rbs_collection_warning(&fake_worktree, Path::new("")), None);
+    }
+
+    #[test]
+    fn test_completion_labels() {
+        use crate::language_servers::label::render_label;
+        use zed_extension_api::lsp::CompletionKind;
+
+        let cases = [
+            (
+                "fetch",
+                CompletionKind::Method,
+                Some("(::Integer index) -> ::String"),
+                Some("::Array#fetch"),
+            ),
+            (
+                "each",
+                CompletionKind::Method,
+                Some("() { (Elem item) -> void } -> self"),
+                Some("::Array#each"),
+            ),
+            (
+                "map",
+                CompletionKind::Method,
+                Some("[U] () { (Elem) -> U } -> ::Array[U]"),
+                None,
+            ),
+            (
+                "empty?",
+                CompletionKind::Method,
+                None,
+                Some("::Array#empty?"),
+            ),
+            (
+                "new",
+                CompletionKind::Function,
+                Some("(?name: ::String?, **untyped) -> instance"),
+                Some("::User.new"),
+            ),
+            (
+                "find",
+                CompletionKind::Method,
+                Some("(*::Integer ids, ?limit: ::Integer) -> (::User | nil)"),
+                Some("::ActiveRecord::FinderMethods#find"),
+            ),
+            ("User", CompletionKind::Class, None, Some("::User")),
+            ("@name", CompletionKind::Field, None, Some("::String?")),
+            (
+                "users",
+                CompletionKind::Variable,
+                None,
+                Some("::Array[::User] | ::Hash[::Symbol, bool]"),
+            ),
+            ("VERSION", CompletionKind::Constant, None, Some("::String")),
+            (
+                "limit:",
+                CompletionKind::Field,
+                None,
+                Some("Keyword argument"),
+            ),
+            ("each", CompletionKind::Snippet, None, None),
+        ];
+
+        let steep = Steep::new();
+        let rendered: Vec<String> = cases
+            .into_iter()
+            .map(|(label, kind, detail, description)| {
+                let heading =
+                    format!("## {kind:?} {label:?} label_details=({detail:?}, {description:?})");
+                let label = steep
+                    .label_for_completion_details(
+                        label.to_string(),
+                        kind,
+                        detail.map(str::to_string),
+                        description.map(str::to_string),
+                    )
+                    .map_or_else(|| "no label\n".to_string(), |label| render_label(&label));
+                format!("{heading}\n{label}")
+            })
+            .collect();
+
+        insta::assert_snapshot!(rendered.join("\n"));
+    }
 }
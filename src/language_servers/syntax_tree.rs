@@ -0,0 +1,167 @@
+use super::{
+    language_server::WorktreeLike,
+    project::{ProjectMarkers, STREERC},
+    settings::NoSettings,
+    LanguageServer,
+};
+
+/// `.streerc` options that `stree lsp` understands. Other options only apply
+/// to the `check`/`format` commands.
+const LSP_OPTIONS: &[&str] = &["--plugins=", "--print-width="];
+
+pub struct SyntaxTree {}
+
+impl LanguageServer for SyntaxTree {
+    const SERVER_ID: &str = "syntax-tree";
+    const EXECUTABLE_NAME: &str = "stree";
+    const GEM_NAME: &str = "syntax_tree";
+    type Settings = NoSettings;
+
+    fn inactive_reason(&self, markers: &ProjectMarkers) -> Option<String> {
+        if markers.has_file(STREERC) || markers.depends_on(Self::GEM_NAME) {
+            None
+        } else {
+            Some(format!(
+                "no {STREERC} found and {} is not in the Gemfile.lock",
+                Self::GEM_NAME
+            ))
+        }
+    }
+
+    fn get_executable_args<T: WorktreeLike>(&self, worktree: &T) -> Vec<String> {
+        let streerc = worktree.read_text_file(STREERC).unwrap_or_default();
+
+        std::iter::once("lsp".to_string())
+            .chain(
+                streerc
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| LSP_OPTIONS.iter().any(|option| line.starts_with(option)))
+                    .map(ToString::to_string),
+            )
+            .collect()
+    }
+}
+
+impl SyntaxTree {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_executor::ScriptedCommandExecutor;
+    use crate::language_servers::{
+        language_server::{FakeHost, FakeWorktree},
+        LanguageServer, SyntaxTree,
+    };
+
+    #[test]
+    fn test_server_id() {
+        assert_eq!(SyntaxTree::SERVER_ID, "syntax-tree");
+    }
+
+    #[test]
+    fn test_executable_name() {
+        assert_eq!(SyntaxTree::EXECUTABLE_NAME, "stree");
+    }
+
+    #[test]
+    fn test_gem_name() {
+        assert_eq!(SyntaxTree::GEM_NAME, "syntax_tree");
+    }
+
+    #[test]
+    fn test_default_use_bundler() {
+        assert!(SyntaxTree::default_use_bundler());
+    }
+
+    #[test]
+    fn test_executable_args_without_streerc() {
+        let syntax_tree = SyntaxTree::new();
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        assert_eq!(syntax_tree.get_executable_args(&mock_worktree), vec!["lsp"]);
+    }
+
+    #[test]
+    fn test_executable_args_with_streerc() {
+        let syntax_tree = SyntaxTree::new();
+        let mut mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+        mock_worktree.add_file(
+            ".streerc".to_string(),
+            Ok("--print-width=100\n--plugins=plugin/trailing_comma,plugin/single_quotes\n\n--ignore-files=vendor/**\n".to_string()),
+        );
+
+        assert_eq!(
+            syntax_tree.get_executable_args(&mock_worktree),
+            vec![
+                "lsp",
+                "--print-width=100",
+                "--plugins=plugin/trailing_comma,plugin/single_quotes"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bundled_binary_includes_streerc_plugins() {
+        let syntax_tree = SyntaxTree::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.set_which("bundle".to_string(), Some("/bin/bundle".to_string()));
+        fake_worktree.add_file(
+            ".streerc".to_string(),
+            Ok("--plugins=plugin/trailing_comma\n".to_string()),
+        );
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("bundle", &["info", "--version", "syntax_tree"], "6.2.0");
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = syntax_tree
+            .resolve_language_server_binary(SyntaxTree::SERVER_ID, &fake_worktree, &host)
+            .expect("Syntax Tree should run through the project bundle");
+
+        assert_eq!(binary.path, "/bin/bundle");
+        assert_eq!(
+            binary.args,
+            Some(vec![
+                "exec".to_string(),
+                "stree".to_string(),
+                "lsp".to_string(),
+                "--plugins=plugin/trailing_comma".to_string()
+            ])
+        );
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_extension_gemset_binary() {
+        let syntax_tree = SyntaxTree::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.set_use_bundler(SyntaxTree::SERVER_ID.to_string(), false);
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success("ruby", &["--version"], "ruby 3.3.0");
+        executor.expect_success("gem", &["list", "--norc", "--exact", "syntax_tree"], "");
+        executor.expect_success(
+            "gem",
+            &[
+                "install",
+                "--norc",
+                "--no-user-install",
+                "--no-format-executable",
+                "--no-document",
+                "syntax_tree",
+            ],
+            "Successfully installed syntax_tree-6.2.0",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        let binary = syntax_tree
+            .resolve_language_server_binary(SyntaxTree::SERVER_ID, &fake_worktree, &host)
+            .expect("Syntax Tree should install into the extension gemset");
+
+        assert!(binary.path.ends_with("/bin/stree"));
+        assert_eq!(binary.args, Some(vec!["lsp".to_string()]));
+        executor.assert_finished();
+    }
+}
@@ -0,0 +1,140 @@
+use super::{
+    language_server::WorktreeLike,
+    project::{ProjectMarkers, TYPEPROF_CONFIG, TYPEPROF_CONFIG_JSONC},
+    settings::NoSettings,
+    LanguageServer,
+};
+use crate::version::Version;
+
+pub struct TypeProf {}
+
+impl LanguageServer for TypeProf {
+    const SERVER_ID: &str = "typeprof";
+    const EXECUTABLE_NAME: &str = "typeprof";
+    const GEM_NAME: &str = "typeprof";
+    type Settings = NoSettings;
+
+    fn inactive_reason(&self, markers: &ProjectMarkers) -> Option<String> {
+        if markers.has_file(TYPEPROF_CONFIG)
+            || markers.has_file(TYPEPROF_CONFIG_JSONC)
+            || markers.depends_on(Self::GEM_NAME)
+        {
+            None
+        } else {
+            Some(format!(
+                "no {TYPEPROF_CONFIG} or {TYPEPROF_CONFIG_JSONC} found and {} is not in the Gemfile.lock",
+                Self::GEM_NAME
+            ))
+        }
+    }
+
+    /// The LSP mode ships with TypeProf 0.30, which requires Ruby 3.3.
+    fn minimum_ruby_version() -> Option<Version> {
+        Version::parse("3.3")
+    }
+
+    fn get_executable_args<T: WorktreeLike>(&self, _worktree: &T) -> Vec<String> {
+        vec!["--lsp".to_string(), "--stdio".to_string()]
+    }
+}
+
+impl TypeProf {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_executor::ScriptedCommandExecutor;
+    use crate::language_servers::{
+        language_server::{FakeHost, FakeWorktree},
+        LanguageServer, TypeProf,
+    };
+
+    #[test]
+    fn test_server_id() {
+        assert_eq!(TypeProf::SERVER_ID, "typeprof");
+    }
+
+    #[test]
+    fn test_executable_name() {
+        assert_eq!(TypeProf::EXECUTABLE_NAME, "typeprof");
+    }
+
+    #[test]
+    fn test_default_use_bundler() {
+        assert!(TypeProf::default_use_bundler());
+    }
+
+    #[test]
+    fn test_executable_args() {
+        let typeprof = TypeProf::new();
+        let mock_worktree = FakeWorktree::new("/path/to/project".to_string());
+
+        assert_eq!(
+            typeprof.get_executable_args(&mock_worktree),
+            vec!["--lsp", "--stdio"]
+        );
+    }
+
+    #[test]
+    fn test_declines_on_old_ruby() {
+        let typeprof = TypeProf::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file("typeprof.conf.json".to_string(), Ok("{}".to_string()));
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success(
+            "ruby",
+            &["--version"],
+            "ruby 3.2.2 (2023-03-30 revision e51014f9c0) [x86_64-linux]",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        let error = typeprof
+            .ensure_active(TypeProf::SERVER_ID, &fake_worktree, &host)
+            .expect_err("TypeProf should not start on Ruby 3.2");
+
+        assert_eq!(
+            error,
+            "typeprof requires Ruby 3.3 or newer, but the project uses Ruby 3.2.2."
+        );
+        assert_eq!(host.statuses().len(), 1);
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_starts_on_supported_ruby() {
+        let typeprof = TypeProf::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file("typeprof.conf.json".to_string(), Ok("{}".to_string()));
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success(
+            "ruby",
+            &["--version"],
+            "ruby 3.4.1 (2024-12-25 revision 48d4efcb85) +PRISM [arm64-darwin24]",
+        );
+        let host = FakeHost::with_executor(executor.clone());
+
+        assert!(typeprof
+            .ensure_active(TypeProf::SERVER_ID, &fake_worktree, &host)
+            .is_ok());
+        assert!(host.statuses().is_empty());
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_skips_ruby_check_without_command_api() {
+        let typeprof = TypeProf::new();
+        let mut fake_worktree = FakeWorktree::new("/path/to/project".to_string());
+        fake_worktree.add_file("typeprof.conf.json".to_string(), Ok("{}".to_string()));
+
+        assert!(typeprof
+            .ensure_active(
+                TypeProf::SERVER_ID,
+                &fake_worktree,
+                &FakeHost::command_free()
+            )
+            .is_ok());
+    }
+}
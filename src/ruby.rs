@@ -1,29 +1,33 @@
-#[cfg(feature = "command_api")]
 mod bundler;
-#[cfg(feature = "command_api")]
 mod command_executor;
-#[cfg(feature = "command_api")]
+mod debugger;
 mod gemset;
 mod language_servers;
+mod version;
 
-use std::collections::HashMap;
 #[cfg(feature = "command_api")]
 use std::path::PathBuf;
+use std::{collections::HashMap, net::Ipv4Addr};
 
 #[cfg(feature = "command_api")]
 use bundler::Bundler;
 #[cfg(feature = "command_api")]
 use command_executor::RealCommandExecutor;
+use debugger::{ComposeTask, LaunchPlan, LaunchProgram, RubyDebugConfig, TcpTarget};
 #[cfg(feature = "command_api")]
-use gemset::{versioned_gem_home, Gemset};
+use gemset::{interpreter_gem_home, Gemset};
 use language_servers::{
-    FuzzyRubyServer, Herb, Kanayago, LanguageServer, Rubocop, RubyLsp, Solargraph, Sorbet, Steep,
+    doctor_output, doctor_report, EnvOverrides, FuzzyRubyServer, GemfileLock, Herb, Kanayago,
+    LanguageServer, Rubocop, RubyLsp, Solargraph, Sorbet, Standard, Steep, SyntaxTree, TypeProf,
+    DOCTOR_COMMAND, GEMFILE_LOCK,
 };
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "command_api")]
+use version::RubyInterpreter;
 use zed_extension_api::{
     self as zed, resolve_tcp_template, DebugAdapterBinary, DebugConfig, DebugRequest,
-    DebugScenario, DebugTaskDefinition, StartDebuggingRequestArguments,
-    StartDebuggingRequestArgumentsRequest, TcpArgumentsTemplate, Worktree,
+    DebugScenario, DebugTaskDefinition, SlashCommand, SlashCommandOutput,
+    StartDebuggingRequestArguments, StartDebuggingRequestArgumentsRequest, TcpArgumentsTemplate,
+    Worktree,
 };
 
 #[derive(Default)]
@@ -31,25 +35,16 @@ struct RubyExtension {
     solargraph: Option<Solargraph>,
     ruby_lsp: Option<RubyLsp>,
     rubocop: Option<Rubocop>,
+    standard: Option<Standard>,
     sorbet: Option<Sorbet>,
     steep: Option<Steep>,
+    syntax_tree: Option<SyntaxTree>,
+    typeprof: Option<TypeProf>,
     herb: Option<Herb>,
     kanayago: Option<Kanayago>,
     fuzzy_ruby_server: Option<FuzzyRubyServer>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct RubyDebugConfig {
-    script_or_command: Option<String>,
-    script: Option<String>,
-    command: Option<String>,
-    #[serde(default)]
-    args: Vec<String>,
-    #[serde(default)]
-    env: HashMap<String, String>,
-    cwd: Option<String>,
-}
-
 impl zed::Extension for RubyExtension {
     fn new() -> Self {
         Self::default()
@@ -73,6 +68,10 @@ impl zed::Extension for RubyExtension {
                 let rubocop = self.rubocop.get_or_insert_with(Rubocop::new);
                 rubocop.language_server_command(language_server_id, worktree)
             }
+            Standard::SERVER_ID => {
+                let standard = self.standard.get_or_insert_with(Standard::new);
+                standard.language_server_command(language_server_id, worktree)
+            }
             Sorbet::SERVER_ID => {
                 let sorbet = self.sorbet.get_or_insert_with(Sorbet::new);
                 sorbet.language_server_command(language_server_id, worktree)
@@ -81,6 +80,14 @@ impl zed::Extension for RubyExtension {
                 let steep = self.steep.get_or_insert_with(Steep::new);
                 steep.language_server_command(language_server_id, worktree)
             }
+            SyntaxTree::SERVER_ID => {
+                let syntax_tree = self.syntax_tree.get_or_insert_with(SyntaxTree::new);
+                syntax_tree.language_server_command(language_server_id, worktree)
+            }
+            TypeProf::SERVER_ID => {
+                let typeprof = self.typeprof.get_or_insert_with(TypeProf::new);
+                typeprof.language_server_command(language_server_id, worktree)
+            }
             Herb::SERVER_ID => {
                 let herb = self.herb.get_or_insert_with(Herb::new);
                 herb.language_server_command(language_server_id, worktree)
@@ -107,7 +114,7 @@ impl zed::Extension for RubyExtension {
         match language_server_id.as_ref() {
             RubyLsp::SERVER_ID => {
                 let ruby = self.ruby_lsp.get_or_insert_with(RubyLsp::new);
-                ruby.language_server_initialization_options(language_server_id, worktree)
+                ruby.language_server_initialization_options(language_server_id.as_ref(), worktree)
             }
             _ => Ok(Some(
                 zed::settings::LspSettings::for_worktree(language_server_id.as_ref(), worktree)
@@ -118,6 +125,28 @@ impl zed::Extension for RubyExtension {
         }
     }
 
+    fn language_server_workspace_configuration(
+        &mut self,
+        language_server_id: &zed::LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> zed::Result<Option<zed::serde_json::Value>> {
+        let id = language_server_id.as_ref();
+        match id {
+            Solargraph::SERVER_ID => Solargraph::workspace_configuration(id, worktree),
+            RubyLsp::SERVER_ID => RubyLsp::workspace_configuration(id, worktree),
+            Rubocop::SERVER_ID => Rubocop::workspace_configuration(id, worktree),
+            Standard::SERVER_ID => Standard::workspace_configuration(id, worktree),
+            Sorbet::SERVER_ID => Sorbet::workspace_configuration(id, worktree),
+            Steep::SERVER_ID => Steep::workspace_configuration(id, worktree),
+            SyntaxTree::SERVER_ID => SyntaxTree::workspace_configuration(id, worktree),
+            TypeProf::SERVER_ID => TypeProf::workspace_configuration(id, worktree),
+            Herb::SERVER_ID => Herb::workspace_configuration(id, worktree),
+            Kanayago::SERVER_ID => Kanayago::workspace_configuration(id, worktree),
+            FuzzyRubyServer::SERVER_ID => FuzzyRubyServer::workspace_configuration(id, worktree),
+            _ => Ok(None),
+        }
+    }
+
     fn label_for_completion(
         &self,
         language_server_id: &zed::LanguageServerId,
@@ -126,6 +155,9 @@ impl zed::Extension for RubyExtension {
         match language_server_id.as_ref() {
             Solargraph::SERVER_ID => self.solargraph.as_ref()?.label_for_completion(completion),
             RubyLsp::SERVER_ID => self.ruby_lsp.as_ref()?.label_for_completion(completion),
+            Sorbet::SERVER_ID => self.sorbet.as_ref()?.label_for_completion(completion),
+            Steep::SERVER_ID => self.steep.as_ref()?.label_for_completion(completion),
+            Kanayago::SERVER_ID => self.kanayago.as_ref()?.label_for_completion(completion),
             _ => None,
         }
     }
@@ -138,10 +170,29 @@ impl zed::Extension for RubyExtension {
         match language_server_id.as_ref() {
             Solargraph::SERVER_ID => self.solargraph.as_ref()?.label_for_symbol(symbol),
             RubyLsp::SERVER_ID => self.ruby_lsp.as_ref()?.label_for_symbol(symbol),
+            Sorbet::SERVER_ID => self.sorbet.as_ref()?.label_for_symbol(symbol),
+            Steep::SERVER_ID => self.steep.as_ref()?.label_for_symbol(symbol),
+            Kanayago::SERVER_ID => self.kanayago.as_ref()?.label_for_symbol(symbol),
             _ => None,
         }
     }
 
+    fn run_slash_command(
+        &self,
+        command: SlashCommand,
+        _args: Vec<String>,
+        worktree: Option<&Worktree>,
+    ) -> Result<SlashCommandOutput, String> {
+        match command.name.as_str() {
+            DOCTOR_COMMAND => {
+                let worktree = worktree
+                    .ok_or_else(|| format!("/{DOCTOR_COMMAND} requires an open project"))?;
+                Ok(doctor_output(doctor_report(worktree, self.steep.as_ref())))
+            }
+            command => Err(format!("unknown slash command: {command}")),
+        }
+    }
+
     fn get_dap_binary(
         &mut self,
         adapter_name: String,
@@ -149,8 +200,88 @@ impl zed::Extension for RubyExtension {
         _: Option<String>,
         worktree: &Worktree,
     ) -> Result<DebugAdapterBinary, String> {
+        let mut configuration: serde_json::Value = serde_json::from_str(&config.config)
+            .map_err(|e| format!("`config` is not a valid JSON: {e:#}"))?;
+        if let Some(configuration) = configuration.as_object_mut() {
+            configuration
+                .entry("cwd")
+                .or_insert_with(|| worktree.root_path().into());
+        }
+
+        let mut ruby_config: RubyDebugConfig = serde_json::from_value(configuration.clone())
+            .map_err(|e| format!("`config` is not a valid rdbg config: {e:#}"))?;
+        // As before `env_file`, the adapter only gets the configured
+        // variables, with `env` passed verbatim on top of the file.
+        let mut envs = EnvOverrides::load(worktree, ruby_config.env_file.as_deref(), None)
+            .map_err(|e| format!("Invalid `env_file`: {e}"))?
+            .apply(Vec::new());
+        let mut env: Vec<(&String, &String)> = ruby_config.env.iter().collect();
+        env.sort();
+        for (key, value) in env {
+            set_env(&mut envs, key.clone(), value.clone());
+        }
+        let debug_env: HashMap<String, String> = envs.iter().cloned().collect();
+
+        let request_type = self.dap_request_kind(adapter_name.clone(), configuration.clone())?;
+        let root = worktree.root_path();
+        if ruby_config.compose_service.is_some() {
+            if request_type == StartDebuggingRequestArgumentsRequest::Launch {
+                return Err("`compose_service` only applies to attach requests".to_string());
+            }
+            ruby_config.apply_compose_service(&root, |file| worktree.read_text_file(file))?;
+        }
+        // rdbg reads `localfsMap` from the request for both launch and attach.
+        if let Some(map) = ruby_config.local_fs_map(&root)? {
+            configuration["localfsMap"] = map.to_string().into();
+        }
+
+        let tcp_connection = config.tcp_connection.unwrap_or(TcpArgumentsTemplate {
+            port: None,
+            host: None,
+            timeout: None,
+        });
+        let tcp_target = TcpTarget::resolve(
+            &ruby_config,
+            &debug_env,
+            tcp_connection.host.map(Ipv4Addr::from),
+            tcp_connection.port,
+        )?;
+
+        let bridge = match request_type {
+            StartDebuggingRequestArgumentsRequest::Launch => {
+                tcp_target.ensure_reachable()?;
+                None
+            }
+            StartDebuggingRequestArgumentsRequest::Attach => {
+                ruby_config.attach_bridge(&tcp_target, &debug_env)?
+            }
+        };
+        if let Some(bridge) = bridge {
+            let ruby = worktree.which("ruby").ok_or_else(|| {
+                "Attaching to this rdbg session requires 'ruby' in the project environment"
+                    .to_string()
+            })?;
+            let connection = resolve_tcp_template(TcpArgumentsTemplate {
+                host: None,
+                port: None,
+                timeout: tcp_connection.timeout,
+            })?;
+
+            return Ok(DebugAdapterBinary {
+                command: Some(ruby),
+                arguments: bridge.arguments(connection.port),
+                connection: Some(connection),
+                cwd: ruby_config.cwd.or(Some(root)),
+                envs,
+                request_args: StartDebuggingRequestArguments {
+                    configuration: configuration.to_string(),
+                    request: request_type,
+                },
+            });
+        }
+
         #[cfg(feature = "command_api")]
-        let (command, mut arguments) = {
+        let (command, mut arguments, rdbg_bundled) = {
             let shell_env = worktree.shell_env();
             let env_vars: Vec<(&str, &str)> = shell_env
                 .iter()
@@ -162,14 +293,15 @@ impl zed::Extension for RubyExtension {
                 let bundle = worktree.which("bundle").ok_or_else(|| {
                     "debug gem present, but unable to find 'bundle' command".to_string()
                 })?;
-                (bundle, vec!["exec".to_string(), "rdbg".to_string()])
+                (bundle, vec!["exec".to_string(), "rdbg".to_string()], true)
             } else if let Some(path) = worktree.which(&adapter_name) {
-                (path, Vec::new())
+                (path, Vec::new(), false)
             } else {
                 let base_dir = std::env::current_dir()
                     .map_err(|e| format!("Failed to get extension directory: {e:#}"))?;
-                let gem_home = versioned_gem_home(&base_dir, &env_vars, &RealCommandExecutor)
+                let ruby = RubyInterpreter::detect(&env_vars, &RealCommandExecutor)
                     .map_err(|e| format!("{:#}", e))?;
+                let gem_home = interpreter_gem_home(&base_dir, &ruby);
                 let gemset = Gemset::new(gem_home, Some(&env_vars), Box::new(RealCommandExecutor));
                 gemset
                     .install_gem("debug")
@@ -177,70 +309,40 @@ impl zed::Extension for RubyExtension {
                 let rdbg = gemset
                     .gem_bin_path("rdbg")
                     .map_err(|e| format!("{:#}", e))?;
-                (rdbg, Vec::new())
+                (rdbg, Vec::new(), false)
             }
         };
 
         #[cfg(not(feature = "command_api"))]
-        let (command, mut arguments) = if let Some(path) = worktree.which(&adapter_name) {
-            (path, Vec::new())
-        } else {
-            return Err(format!(
-                "Unable to find '{adapter_name}' command in the project environment"
-            ));
-        };
-
-        let tcp_connection = config.tcp_connection.unwrap_or(TcpArgumentsTemplate {
-            port: None,
-            host: None,
-            timeout: None,
-        });
-        let mut connection = resolve_tcp_template(tcp_connection)?;
-        let mut configuration: serde_json::Value = serde_json::from_str(&config.config)
-            .map_err(|e| format!("`config` is not a valid JSON: {e:#}"))?;
-        if let Some(configuration) = configuration.as_object_mut() {
-            configuration
-                .entry("cwd")
-                .or_insert_with(|| worktree.root_path().into());
-        }
-
-        let ruby_config: RubyDebugConfig = serde_json::from_value(configuration.clone())
-            .map_err(|e| format!("`config` is not a valid rdbg config: {e:#}"))?;
-
-        if let Some(host) = ruby_config.env.get("RUBY_DEBUG_HOST") {
-            connection.host = host
-                .parse::<std::net::Ipv4Addr>()
-                .map(|ip_addr| ip_addr.into())
-                .map_err(|_| format!("Invalid host '{host}' specified via RUBY_DEBUG_HOST"))?;
-        } else {
-            arguments.push(format!("--host={}", connection.host));
-        }
+        let (command, mut arguments, rdbg_bundled) =
+            if let Some(path) = worktree.which(&adapter_name) {
+                (path, Vec::new(), false)
+            } else {
+                return Err(format!(
+                    "Unable to find '{adapter_name}' command in the project environment"
+                ));
+            };
 
-        if let Some(port) = ruby_config.env.get("RUBY_DEBUG_PORT") {
-            connection.port = port.parse::<u16>().map_err(|_| {
-                format!("Invalid port number '{port}' specified via RUBY_DEBUG_PORT")
-            })?;
-        } else {
-            arguments.push(format!("--port={}", connection.port));
-        }
+        let connection = resolve_tcp_template(TcpArgumentsTemplate {
+            host: tcp_target.address.map(u32::from),
+            port: tcp_target.port,
+            timeout: tcp_connection.timeout,
+        })?;
+        arguments.extend(tcp_target.arguments(connection.port));
 
-        let request_type = self.dap_request_kind(adapter_name.clone(), configuration.clone())?;
         match request_type {
             StartDebuggingRequestArgumentsRequest::Launch => {
-                if !ruby_config.env.contains_key("RUBY_DEBUG_OPEN") {
-                    arguments.push("--open".to_string());
+                arguments.extend(ruby_config.launch_arguments(&debug_env)?);
+                for (key, value) in ruby_config.launch_env(&debug_env)? {
+                    set_env(&mut envs, key, value);
                 }
-                arguments.push("--stop-at-load".to_string());
 
-                let (is_command, program) = if let Some(script) = &ruby_config.script {
-                    (false, script.clone())
+                let program = if let Some(script) = &ruby_config.script {
+                    LaunchProgram::Script(script)
                 } else if let Some(command) = &ruby_config.command {
-                    (true, command.clone())
+                    LaunchProgram::Command(command)
                 } else if let Some(command_or_script) = &ruby_config.script_or_command {
-                    (
-                        worktree.which(command_or_script).is_some(),
-                        command_or_script.clone(),
-                    )
+                    LaunchProgram::ScriptOrCommand(command_or_script)
                 } else {
                     return Err(
                         "Ruby debug config must have 'script', 'command', or 'script_or_command' arg"
@@ -248,12 +350,19 @@ impl zed::Extension for RubyExtension {
                     );
                 };
 
-                if is_command {
-                    arguments.push("--command".to_string());
-                }
-                arguments.push(program);
-                arguments.push("--".to_string());
-                arguments.extend(ruby_config.args);
+                let lockfile = worktree
+                    .read_text_file(GEMFILE_LOCK)
+                    .ok()
+                    .map(|content| GemfileLock::parse(&content));
+                let plan = LaunchPlan::new(
+                    program,
+                    &ruby_config.args,
+                    lockfile.as_ref(),
+                    rdbg_bundled,
+                    &root,
+                    |path| worktree.read_text_file(path).is_ok(),
+                )?;
+                arguments.extend(plan.arguments());
             }
             StartDebuggingRequestArgumentsRequest::Attach => {
                 arguments.push("--attach".to_string());
@@ -265,7 +374,7 @@ impl zed::Extension for RubyExtension {
             arguments,
             connection: Some(connection),
             cwd: ruby_config.cwd.or(Some(worktree.root_path())),
-            envs: ruby_config.env.into_iter().collect(),
+            envs,
             request_args: StartDebuggingRequestArguments {
                 configuration: configuration.to_string(),
                 request: request_type,
@@ -298,13 +407,16 @@ impl zed::Extension for RubyExtension {
     ) -> Result<DebugScenario, String> {
         match zed_scenario.request {
             DebugRequest::Launch(launch) => {
+                // `program` is a single path or executable, quoted so that
+                // splitting `script_or_command` keeps it whole.
+                let program = shlex::try_quote(&launch.program)
+                    .map_err(|e| format!("Invalid program `{}`: {e}", launch.program))?;
                 let config = RubyDebugConfig {
-                    script_or_command: Some(launch.program),
-                    script: None,
-                    command: None,
+                    script_or_command: Some(program.into_owned()),
                     args: launch.args,
                     env: launch.envs.into_iter().collect(),
                     cwd: launch.cwd.clone(),
+                    ..Default::default()
                 };
 
                 let config = serde_json::to_value(config)
@@ -319,14 +431,10 @@ impl zed::Extension for RubyExtension {
                     build: None,
                 })
             }
-            DebugRequest::Attach(_) => {
+            DebugRequest::Attach(attach) => {
                 let config = RubyDebugConfig {
-                    script_or_command: None,
-                    script: None,
-                    command: None,
-                    args: vec![],
-                    env: Default::default(),
-                    cwd: None,
+                    pid: attach.process_id,
+                    ..Default::default()
                 };
 
                 let config = serde_json::to_value(config)
@@ -355,19 +463,34 @@ impl zed::Extension for RubyExtension {
             return None;
         }
 
-        let config = RubyDebugConfig {
-            script_or_command: None,
-            script: None,
-            command: Some(build_task.command),
-            args: build_task.args,
-            env: build_task.env.into_iter().collect(),
-            cwd: build_task.cwd,
+        // A task running a compose service attaches to that service instead of
+        // launching the task under rdbg.
+        let compose_task = ComposeTask::parse(&build_task.command, &build_task.args);
+        let request = if compose_task.is_some() {
+            "attach"
+        } else {
+            "launch"
+        };
+        let config = match compose_task {
+            Some(task) => RubyDebugConfig {
+                cwd: build_task.cwd,
+                compose_service: Some(task.service),
+                compose_file: task.file,
+                ..Default::default()
+            },
+            None => RubyDebugConfig {
+                command: Some(build_task.command),
+                args: build_task.args,
+                env: build_task.env.into_iter().collect(),
+                cwd: build_task.cwd,
+                ..Default::default()
+            },
         };
 
         let config = match serde_json::to_value(config) {
             Ok(mut value) => {
                 let obj = value.as_object_mut()?;
-                obj.entry("request").or_insert("launch".into());
+                obj.entry("request").or_insert(request.into());
                 value.to_string()
             }
             Err(_) => return None,
@@ -383,4 +506,12 @@ impl zed::Extension for RubyExtension {
     }
 }
 
+/// Sets `key` in `envs`, replacing an earlier value in place.
+fn set_env(envs: &mut Vec<(String, String)>, key: String, value: String) {
+    match envs.iter_mut().find(|(existing, _)| *existing == key) {
+        Some(existing) => existing.1 = value,
+        None => envs.push((key, value)),
+    }
+}
+
 zed_extension_api::register_extension!(RubyExtension);
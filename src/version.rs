@@ -0,0 +1,172 @@
+use crate::command_executor::CommandExecutor;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::{cmp::Ordering, fmt, sync::LazyLock};
+
+/// A dotted numeric version such as Ruby's `3.3.0` or a gem's `1.60.2`.
+///
+/// Parsing stops at the first non-numeric segment, so prerelease suffixes like
+/// `3.4.0preview1` or `0.23.0.pre` compare equal to their release.
+#[derive(Clone, Debug, Eq)]
+pub struct Version(Vec<u64>);
+
+impl Version {
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut segments = Vec::new();
+
+        for segment in version.trim().split('.') {
+            let digits: String = segment.chars().take_while(char::is_ascii_digit).collect();
+            let Ok(number) = digits.parse() else {
+                break;
+            };
+            segments.push(number);
+            if digits.len() != segment.len() {
+                break;
+            }
+        }
+
+        (!segments.is_empty()).then_some(Self(segments))
+    }
+
+    fn segment(&self, index: usize) -> u64 {
+        self.0.get(index).copied().unwrap_or(0)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (0..self.0.len().max(other.0.len()))
+            .map(|index| self.segment(index).cmp(&other.segment(index)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let segments: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        write!(f, "{}", segments.join("."))
+    }
+}
+
+/// The `ruby` interpreter found in an environment, as `ruby --version`
+/// describes it, such as `ruby 3.3.0 (2023-12-25 revision 5124f9ac75) [arm64-darwin23]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RubyInterpreter {
+    description: String,
+}
+
+impl RubyInterpreter {
+    pub fn detect(envs: &[(&str, &str)], executor: &dyn CommandExecutor) -> Result<Self> {
+        let output = executor
+            .execute("ruby", &["--version"], envs)
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("Failed to detect Ruby version")?;
+
+        match output.status {
+            Some(0) => Ok(Self {
+                description: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            }),
+            Some(status) => bail!("Ruby version check failed with status {status}"),
+            None => bail!("Failed to execute ruby --version"),
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The language version, which other interpreters such as JRuby do not
+    /// report in the same place.
+    pub fn version(&self) -> Result<Version> {
+        static RUBY_VERSION_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^ruby (\d+\.\d+\.\d+)").unwrap());
+
+        RUBY_VERSION_REGEX
+            .captures(&self.description)
+            .and_then(|captures| Version::parse(&captures[1]))
+            .with_context(|| format!("Unable to parse Ruby version from '{}'", self.description))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_executor::ScriptedCommandExecutor;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).expect("version should parse")
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(version("3.3.0").to_string(), "3.3.0");
+        assert_eq!(version("1.60").to_string(), "1.60");
+        assert_eq!(version("3.4.0preview1").to_string(), "3.4.0");
+        assert_eq!(version("0.23.0.pre").to_string(), "0.23.0");
+        assert_eq!(Version::parse("main"), None);
+        assert_eq!(Version::parse(""), None);
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(version("3.4.1") > version("3.4.0"));
+        assert!(version("3.10") > version("3.9.9"));
+        assert!(version("1.60.2") < version("1.61"));
+        assert_eq!(version("3.3"), version("3.3.0"));
+    }
+
+    #[test]
+    fn test_ruby_interpreter_version() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success(
+            "ruby",
+            &["--version"],
+            "ruby 3.4.1 (2024-12-25 revision 48d4efcb85) +PRISM [arm64-darwin24]\n",
+        );
+
+        let result = RubyInterpreter::detect(&[], &executor)
+            .and_then(|ruby| ruby.version())
+            .expect("version should parse");
+
+        assert_eq!(result, version("3.4.1"));
+        executor.assert_finished();
+    }
+
+    #[test]
+    fn test_ruby_interpreter_version_unparseable() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_success(
+            "ruby",
+            &["--version"],
+            "jruby 9.4.5.0 (3.1.4) 2023-11-02 1abae2700f OpenJDK 64-Bit Server VM",
+        );
+
+        let ruby = RubyInterpreter::detect(&[], &executor).expect("ruby should run");
+        let error = ruby.version().expect_err("jruby is not parsed");
+
+        assert!(format!("{error:#}").contains("Unable to parse Ruby version"));
+    }
+
+    #[test]
+    fn test_ruby_interpreter_version_command_failure() {
+        let executor = ScriptedCommandExecutor::new();
+        executor.expect_failure("ruby", &["--version"], 127, "ruby: command not found");
+
+        let error = RubyInterpreter::detect(&[], &executor).expect_err("command should fail");
+
+        assert!(format!("{error:#}").contains("status 127"));
+    }
+}